@@ -1,59 +1,996 @@
-use crossterm::event::{read, Event, Event::Key, KeyCode::Char, KeyEvent, KeyModifiers, KeyCode};
+use crossterm::event::{Event, KeyCode::Char, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use crossterm::style::Color;
 use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+mod buffer;
+mod clipboard;
+mod commands;
+mod config;
+mod document;
+mod file_io;
+mod gutter;
+mod history;
+mod keymap;
+mod line;
+mod mode;
+mod motion;
+mod mouse;
+mod palette;
+mod registers;
+mod registry;
+mod search;
+mod selection;
+mod status;
+mod substitute;
 mod terminal;
-use terminal::{Terminal, Size, Position};
+mod undo_store;
+mod view;
+mod wrap;
+use buffer::Location;
+use clipboard::ClipboardProvider;
+use document::Document;
+use keymap::{Key, Keymaps, Lookup};
+use line::Line;
+use mode::Mode;
+use motion::{Motion, Operator};
+use mouse::Clicks;
+use palette::Palette;
+use registers::Registers;
+use registry::{Action, Registry};
+use search::Search;
+use status::Message;
+use selection::{Selection, SelectionKind};
+use substitute::Substitution;
+pub use config::ConfigError;
+pub use gutter::{LineNumbers, Sign};
+pub use terminal::{Cell, CrosstermTerminal, MemoryTerminal, Position, Size, Terminal};
+use terminal::Frame;
+use view::View;
+
+const STATUS_ROWS: u16 = 2; // the status bar and the message line below it
 
 pub struct Editor {
     should_quit: bool,
-    terminal : Terminal, // should be an interface
+    terminal: Box<dyn Terminal>,
+    document: Document,
+    view: View,
+    mode: Mode,
+    registry: Registry,
+    keymaps: Keymaps,
+    pending_keys: Vec<Key>,                          // start of a key sequence like `g g`
+    count: Option<usize>,                            // typed before a command in normal and visual mode
+    pending_operator: Option<(Operator, Option<usize>)>, // waiting for its motion, with its own count
+    command_line: String,
+    command_selection: Option<Selection>, // the selection when `:` was typed in visual mode
+    substitution: Option<Substitution>,   // a `:s///c` waiting for an answer
+    palette: Palette,
+    search: Search,
+    frame: Frame,         // the screen drawn off screen, before it goes to the terminal
+    shown: Option<Frame>, // what the terminal shows, None when it has to be drawn afresh
+    message: Option<Message>, // on the message line until it expires or another replaces it
+    viz_org_cursor_pos: Location, // where the selection started
+    clicks: Clicks,
+    registers: Registers,
+    clipboard: Box<dyn ClipboardProvider>,
+    pending_register: Option<char>, // picked with `"x`, used by the next yank or paste
+    awaiting_register: bool,        // `"` was typed, the next char names a register
+    undo_dir: Option<PathBuf>,    // where undo history is persisted, None disables it
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self::with_terminal(Box::new(CrosstermTerminal))
+    }
 }
 
 impl Editor {
-    pub const fn default() -> Self {
-        let terminal = Terminal::default();
-        Self { should_quit: false , terminal: terminal}
+    #[must_use]
+    pub fn with_terminal(terminal: Box<dyn Terminal>) -> Self {
+        Self {
+            should_quit: false,
+            terminal,
+            document: Document::default(),
+            view: View::default(),
+            mode: Mode::default(),
+            registry: Registry::default(),
+            keymaps: Keymaps::default(),
+            pending_keys: Vec::new(),
+            count: None,
+            pending_operator: None,
+            command_line: String::new(),
+            command_selection: None,
+            substitution: None,
+            palette: Palette::default(),
+            search: Search::default(),
+            frame: Frame::new(Size::default()),
+            shown: None,
+            message: None,
+            viz_org_cursor_pos: Location::default(),
+            clicks: Clicks::default(),
+            registers: Registers::default(),
+            clipboard: clipboard::detect(),
+            pending_register: None,
+            awaiting_register: false,
+            undo_dir: undo_store::default_dir(),
+        }
+    }
+    // Keep undo history in another directory, or not at all with None
+    pub fn set_undo_dir(&mut self, dir: Option<PathBuf>) {
+        self.undo_dir = dir;
+    }
+    // Take key bindings from a config file on top of the defaults, see config::default_path
+    pub fn load_config(&mut self, path: &Path) -> Result<(), ConfigError> {
+        let config = config::load(path, &self.registry)?;
+        self.keymaps = config.keymaps;
+        self.view.gutter.line_numbers = config.settings.line_numbers;
+        self.view.side_scroll_margin = config.settings.side_scroll_margin;
+        self.view.soft_wrap.enabled = config.settings.soft_wrap;
+        self.view.soft_wrap.indicator = config.settings.wrap_indicator;
+        self.view.soft_wrap.at_words = config.settings.wrap_at_words;
+        Ok(())
+    }
+    pub fn set_line_numbers(&mut self, line_numbers: LineNumbers) {
+        self.view.gutter.line_numbers = line_numbers;
+    }
+    // Columns kept in view left and right of the cursor when scrolling sideways
+    pub fn set_side_scroll_margin(&mut self, margin: usize) {
+        self.view.side_scroll_margin = margin;
+    }
+    // Long lines go on over the rows below instead of scrolling sideways
+    pub fn set_soft_wrap(&mut self, enabled: bool) {
+        self.view.soft_wrap.enabled = enabled;
+    }
+    // Printed at the start of the rows a wrapped line goes on over
+    pub fn set_wrap_indicator(&mut self, indicator: &str) {
+        self.view.soft_wrap.indicator = indicator.to_string();
+    }
+    // Put a marker next to a line, replacing the one its group had there
+    pub fn set_sign(&mut self, sign: Sign) {
+        self.view.gutter.set_sign(sign);
+    }
+    pub fn clear_signs(&mut self, group: &str) {
+        self.view.gutter.clear_signs(group);
+    }
+    #[must_use]
+    pub fn default_config_path() -> Option<PathBuf> {
+        config::default_path()
+    }
+    // Open a file into the editor, a path that doesn't exist yet becomes a new file
+    pub fn open(&mut self, path: &Path) -> Result<(), Error> {
+        self.document = Document::open(path, self.undo_dir.as_deref())?;
+        // Signs belong to the file they were set on, the settings stay
+        let previous = std::mem::take(&mut self.view);
+        self.view = View {
+            size: previous.size,
+            side_scroll_margin: previous.side_scroll_margin,
+            soft_wrap: previous.soft_wrap,
+            ..View::default()
+        };
+        self.view.gutter.line_numbers = previous.gutter.line_numbers;
+        Ok(())
     }
     pub fn run(&mut self) {
-        self.terminal.initialize().unwrap();
+        self.initialize().unwrap();
         let result = self.repl();
         self.terminal.terminate().unwrap();
         result.unwrap();
     }
 
+    // Initialize the terminal, display the welcome screen, and record terminal size
+    pub fn initialize(&mut self) -> Result<(), Error> {
+        self.terminal.initialize()?;
+
+        // Loop until 'Ctrl + q' is pressed, opening a file skips straight to its contents
+        if self.document.filename().is_none() {
+            while self.display_welcome_screen()? {}
+            self.terminal.clear_screen()?;
+        }
+
+        // Update and record the terminal size
+        self.resize(self.terminal.size()?);
+        self.draw()
+    }
+
+    // The text area is what the status bar and the message line leave of the screen
+    fn resize(&mut self, size: Size) {
+        self.frame = Frame::new(size);
+        self.shown = None;
+        self.view.size = Size { height: size.height.saturating_sub(STATUS_ROWS), width: size.width };
+        self.view.scroll_viewport(&self.document);
+    }
+
+    // Display the welcome screen and check for 'Ctrl + q' to exit
+    fn display_welcome_screen(&mut self) -> Result<bool, Error> {
+        let size = self.terminal.size()?;
+        self.terminal.move_cursor_to(Position { x: size.width / 2, y: size.height / 2 })?; // Move cursor to center
+        self.terminal.print("Welcome to Crab, your fav text editor!!")?; // Display welcome message
+        self.terminal.execute()?; // Execute the print command
+
+        // Check if the key is 'q' with Control modifier
+        let event = self.terminal.read_event()?;
+        Ok(matches!(event, Event::Key(KeyEvent { code: Char('q'), modifiers: KeyModifiers::CONTROL, .. })))
+    }
+
     fn repl(&mut self) -> Result<(), Error> {
         loop {
             self.refresh_screen()?;
             if self.should_quit {
                 break;
             }
-            let event = read()?;
-            self.evaluate_event(&event);
+            // Wake up to take the message down once it expires
+            if let Some(timeout) = self.message.as_ref().map(|message| message.remaining(Instant::now())) {
+                if !self.terminal.poll_event(timeout)? {
+                    self.draw()?;
+                    continue;
+                }
+            }
+            let event = self.terminal.read_event()?;
+            self.evaluate_event(&event)?;
+        }
+        Ok(())
+    }
+    pub fn evaluate_event(&mut self, event: &Event) -> Result<(), Error> {
+        match event {
+            Event::Key(event) => {
+                self.handle_key(Key::from(*event))?;
+                // Scroll the viewport and redraw
+                self.clamp_cursor();
+                self.view.scroll_viewport(&self.document);
+            }
+            // Lay out again for the new size, which scrolls the cursor back into view
+            Event::Resize(width, height) => self.resize(Size { height: *height, width: *width }),
+            Event::Mouse(event) => self.handle_mouse(*event)?,
+            Event::Paste(text) => {
+                self.paste_text(text)?;
+                self.clamp_cursor();
+                self.view.scroll_viewport(&self.document);
+            }
+            _ => return Ok(()),
+        }
+        self.draw()
+    }
+
+    // Clicks put the cursor down, double and triple clicks select a word and a line and dragging
+    // selects from where the button went down. The wheel scrolls without moving the cursor
+    fn handle_mouse(&mut self, event: MouseEvent) -> Result<(), Error> {
+        if !matches!(self.mode, Mode::Normal | Mode::Insert | Mode::Visual(_) | Mode::Select(_)) {
+            return Ok(());
+        }
+        let position = Position { x: event.column, y: event.row };
+        match event.kind {
+            MouseEventKind::ScrollUp => self.view.scroll_by(&self.document, false, mouse::WHEEL_ROWS),
+            MouseEventKind::ScrollDown => self.view.scroll_by(&self.document, true, mouse::WHEEL_ROWS),
+            MouseEventKind::Down(MouseButton::Left) => {
+                let Some(location) = self.view.location_at(&self.document, position) else {
+                    return Ok(());
+                };
+                self.pending_keys.clear();
+                self.count = None;
+                self.document.seal_undo_step();
+                match self.clicks.click(position, Instant::now()) {
+                    1 => {
+                        if self.mode.selection_kind().is_some() {
+                            self.leave_selection()?;
+                        }
+                        self.view.cursor = location;
+                    }
+                    2 => {
+                        let (start, mut end) = motion::word_around(&self.document, location);
+                        if !matches!(self.mode, Mode::Insert | Mode::Select(_)) {
+                            // Visual mode takes the grapheme under the cursor as well
+                            end.col = self.document.line(end.line).prev_boundary(end.col).max(start.col);
+                        }
+                        self.select_with_mouse(SelectionKind::Char, start, end)?;
+                    }
+                    _ => self.select_with_mouse(SelectionKind::Line, location, location)?,
+                }
+                self.clamp_cursor();
+                self.view.scroll_viewport(&self.document);
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                let Some(location) = self.view.location_at(&self.document, position) else {
+                    return Ok(());
+                };
+                if self.mode.selection_kind().is_none() {
+                    self.select_with_mouse(SelectionKind::Char, self.view.cursor, location)?;
+                }
+                self.view.cursor = location;
+                self.view.scroll_viewport(&self.document);
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    // Select from `anchor` to `cursor`, in select mode when coming from insert or select mode
+    // and in visual mode otherwise
+    fn select_with_mouse(&mut self, kind: SelectionKind, anchor: Location, cursor: Location) -> Result<(), Error> {
+        let mode = if matches!(self.mode, Mode::Insert | Mode::Select(_)) { Mode::Select(kind) } else { Mode::Visual(kind) };
+        self.viz_org_cursor_pos = anchor;
+        self.view.cursor = cursor;
+        self.set_mode(mode)
+    }
+
+    // Look the key up in the current mode's table, collecting counts and multi-key sequences
+    fn handle_key(&mut self, key: Key) -> Result<(), Error> {
+        if self.awaiting_register {
+            self.awaiting_register = false;
+            self.pending_register = key.plain_char().filter(|name| Registers::is_valid_name(*name));
+            return Ok(());
+        }
+        let counts = matches!(self.mode, Mode::Normal | Mode::Visual(_));
+        if let Some(digit) = key.plain_char().and_then(|c| c.to_digit(10)) {
+            if counts && self.pending_keys.is_empty() && (digit > 0 || self.count.is_some()) {
+                self.count = Some(self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit as usize));
+                return Ok(());
+            }
+        }
+        self.pending_keys.push(key);
+        match self.keymaps.for_mode(self.mode).lookup(&self.pending_keys) {
+            Lookup::Pending => Ok(()),
+            Lookup::Found(command) => {
+                self.pending_keys.clear();
+                let count = self.count.take();
+                self.run_command(command, count).map(|_| ())
+            }
+            Lookup::Unbound => {
+                let keys = std::mem::take(&mut self.pending_keys);
+                self.count = None;
+                self.pending_operator = None;
+                match (keys.as_slice(), self.mode) {
+                    ([key], mode) => self.type_key(*key, mode),
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+
+    // Run a command from the registry by name, false if there is no such command
+    fn run_command(&mut self, name: &str, count: Option<usize>) -> Result<bool, Error> {
+        let Some(command) = self.registry.get(name) else {
+            return Ok(false);
+        };
+        match command.action {
+            Action::Run(handler) => handler(self, count)?,
+            Action::Motion(motion) => self.apply_motion(motion, count)?,
+            Action::Operator(operator) => self.apply_operator(operator, count)?,
+        }
+        Ok(true)
+    }
+
+    // Keys without a binding type text in insert, select and command mode
+    fn type_key(&mut self, key: Key, mode: Mode) -> Result<(), Error> {
+        let Some(c) = key.plain_char() else {
+            return Ok(());
+        };
+        match mode {
+            Mode::Insert => self.view.cursor = self.document.insert_char(self.view.cursor, c),
+            Mode::Command => self.command_line.push(c),
+            Mode::Search => {
+                self.search.query.push(c);
+                self.update_search();
+            }
+            Mode::Palette => {
+                self.palette.query.push(c);
+                self.palette.selected = 0;
+            }
+            Mode::Select(SelectionKind::Block) => {
+                if let Some(selection) = self.selection() {
+                    self.type_in_block(&selection, c);
+                }
+            }
+            Mode::Select(_) => {
+                // Typing replaces the selection
+                if let Some(selection) = self.selection() {
+                    let (start, end) = match selection.kind {
+                        // Lines are typed over, their line breaks stay
+                        SelectionKind::Line => {
+                            let (first, last) = selection.lines();
+                            (Location { line: first, col: 0 }, Location { line: last, col: self.document.line_len(last) })
+                        }
+                        _ => selection.ranges(&self.document)[0],
+                    };
+                    self.view.cursor = self.document.replace_range(start, end, &c.to_string(), self.view.cursor);
+                    self.leave_selection()?;
+                }
+            }
+            Mode::Normal | Mode::Visual(_) | Mode::Confirm => (),
         }
         Ok(())
     }
-    fn evaluate_event(&mut self, event: &Event) {
-        if let Key(KeyEvent {
-            code, modifiers, ..
-        }) = event
+
+    // Text pasted into the terminal goes in as it is and undoes in one step, rather than as keys
+    // each run through the keymaps. Terminals send line breaks as \r, prompts take one line
+    fn paste_text(&mut self, text: &str) -> Result<(), Error> {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        match self.mode {
+            Mode::Normal | Mode::Insert => {
+                self.document.seal_undo_step();
+                self.view.cursor = self.document.insert_text(self.view.cursor, &text);
+                self.document.seal_undo_step();
+            }
+            Mode::Visual(_) | Mode::Select(_) => {
+                if let Some(selection) = self.selection() {
+                    self.document.begin_undo_group();
+                    self.delete_selection(&selection);
+                    self.view.cursor = self.document.insert_text(self.view.cursor, &text);
+                    self.document.end_undo_group();
+                }
+                self.leave_selection()?;
+            }
+            Mode::Command => self.command_line.push_str(text.lines().next().unwrap_or_default()),
+            Mode::Search => {
+                self.search.query.push_str(text.lines().next().unwrap_or_default());
+                self.update_search();
+            }
+            Mode::Palette => {
+                self.palette.query.push_str(text.lines().next().unwrap_or_default());
+                self.palette.selected = 0;
+            }
+            Mode::Confirm => (),
+        }
+        Ok(())
+    }
+
+    // Move to the first match from where the prompt opened, or back there while nothing matches
+    fn update_search(&mut self) {
+        if !self.find_match(self.search.origin, true, true) {
+            self.view.cursor = self.search.origin;
+        }
+    }
+
+    // Put the cursor on the next or previous match, saying so when the search went round the end
+    fn find_match(&mut self, from: Location, forward: bool, inclusive: bool) -> bool {
+        let Some((location, wrapped)) = self.search.find(&self.document, from, forward, inclusive) else {
+            if !self.search.query.is_empty() {
+                self.show_error(format!("Pattern not found: {}", self.search.query));
+            }
+            return false;
+        };
+        self.view.cursor = location;
+        if wrapped {
+            let message = if forward { "search hit BOTTOM, continuing at TOP" } else { "search hit TOP, continuing at BOTTOM" };
+            self.show_message(message);
+        }
+        true
+    }
+
+    // Run a parsed `:s`, everything it replaces is undone in one step, even over several prompts
+    fn start_substitution(&mut self, command: Result<substitute::Command, String>) -> Result<(), Error> {
+        let substitution = command.and_then(|command| {
+            let (start, end) = self.substitution_range(command.range).ok_or("No selection")?;
+            Ok((Substitution::new(&command, &self.document, start, end)?, command.confirm))
+        });
+        let (mut substitution, confirm) = match substitution {
+            Ok(substitution) => substitution,
+            Err(message) => {
+                self.show_error(message);
+                return Ok(());
+            }
+        };
+        self.document.begin_undo_group();
+        if confirm {
+            if let Some(found) = substitution.advance(&self.document) {
+                self.view.cursor = self.document.char_to_location(found.start);
+                self.substitution = Some(substitution);
+                return self.set_mode(Mode::Confirm);
+            }
+        } else if let Some(last) = substitution.replace_rest(&mut self.document, self.view.cursor) {
+            self.view.cursor = last;
+        }
+        self.finish_substitution(&substitution)
+    }
+
+    // Chars `[start, end)` the substitution may touch, None for a selection when there was none
+    fn substitution_range(&self, range: substitute::Range) -> Option<(usize, usize)> {
+        let line_start = |line: usize| {
+            if line > self.document.last_line() {
+                self.document.len_chars()
+            } else {
+                self.document.location_to_char(Location { line, col: 0 })
+            }
+        };
+        let (start, end) = match range {
+            substitute::Range::CurrentLine => (line_start(self.view.cursor.line), line_start(self.view.cursor.line + 1)),
+            substitute::Range::Whole => (0, self.document.len_chars()),
+            substitute::Range::Lines(first, last) => (line_start(first.saturating_sub(1)), line_start(last)),
+            substitute::Range::Selection => {
+                let selection = self.command_selection?;
+                let ranges = selection.ranges(&self.document);
+                match selection.kind {
+                    SelectionKind::Char => (self.document.location_to_char(ranges[0].0), self.document.location_to_char(ranges[0].1)),
+                    // A block works on its lines, the columns don't make a range of text
+                    SelectionKind::Line | SelectionKind::Block => {
+                        let (first, last) = selection.lines();
+                        (line_start(first), line_start(last + 1))
+                    }
+                }
+            }
+        };
+        Some((start, end))
+    }
+
+    // Answer the confirm prompt, then move on to the next match or finish
+    fn confirm_substitution(&mut self, replace: bool, all: bool) -> Result<(), Error> {
+        let Some(mut substitution) = self.substitution.take() else {
+            return self.set_mode(Mode::Normal);
+        };
+        if replace {
+            if let Some(end) = substitution.replace_current(&mut self.document, self.view.cursor) {
+                self.view.cursor = end;
+            }
+        } else {
+            substitution.skip();
+        }
+        if all {
+            if let Some(last) = substitution.replace_rest(&mut self.document, self.view.cursor) {
+                self.view.cursor = last;
+            }
+        } else if let Some(found) = substitution.advance(&self.document) {
+            self.view.cursor = self.document.char_to_location(found.start);
+            self.substitution = Some(substitution);
+            return Ok(());
+        }
+        self.finish_substitution(&substitution)
+    }
+
+    fn finish_substitution(&mut self, substitution: &Substitution) -> Result<(), Error> {
+        self.document.end_undo_group();
+        let plural = |n: usize, what: &str| if n == 1 { format!("1 {what}") } else { format!("{n} {what}s") };
+        if substitution.count == 0 {
+            self.show_error(format!("Pattern not found: {}", substitution.pattern));
+        } else {
+            self.show_message(format!("{} on {}", plural(substitution.count, "substitution"), plural(substitution.lines, "line")));
+        }
+        self.set_mode(Mode::Normal)
+    }
+
+    fn show_message(&mut self, text: impl Into<String>) {
+        self.message = Some(Message::info(text));
+    }
+
+    fn show_error(&mut self, text: impl Into<String>) {
+        self.message = Some(Message::error(text));
+    }
+
+    fn move_down_or_split(&mut self) {
+        if !self.view.move_down(&self.document) {
+            // Optionally, add a new line if at the end
+            self.view.cursor = self.document.insert_newline(self.view.cursor);
+        }
+    }
+
+    fn set_mode(&mut self, mode: Mode) -> Result<(), Error> {
+        self.mode = mode;
+        self.pending_operator = None;
+        self.pending_register = None;
+        self.terminal.set_cursor_style(mode.cursor_style())
+    }
+
+    // Start selecting, entering the mode that's already active leaves it and another one only
+    // changes how the selection is taken
+    fn enter_selection(&mut self, mode: Mode) -> Result<(), Error> {
+        if self.mode == mode {
+            return self.leave_selection();
+        }
+        if self.mode.selection_kind().is_none() {
+            self.viz_org_cursor_pos = self.view.cursor;
+        }
+        self.set_mode(mode)
+    }
+
+    // Select mode goes back to insert, everything else to normal
+    fn leave_selection(&mut self) -> Result<(), Error> {
+        self.set_mode(if matches!(self.mode, Mode::Select(_)) { Mode::Insert } else { Mode::Normal })
+    }
+
+    // Charwise visual mode selects the grapheme under its later end too, like vim. Select mode
+    // stops before the cursor, like selecting with shift and the arrows elsewhere
+    fn selection(&self) -> Option<Selection> {
+        let kind = self.mode.selection_kind()?;
+        let (mut anchor, mut cursor) = (self.viz_org_cursor_pos, self.view.cursor);
+        if self.mode == Mode::Visual(SelectionKind::Char) {
+            let later = if cursor >= anchor { &mut cursor } else { &mut anchor };
+            later.col = self.document.line(later.line).next_boundary(later.col);
+        }
+        Some(Selection { kind, anchor, cursor })
+    }
+
+    // Normal mode keeps the cursor on a grapheme, only typing goes past the end of the line
+    fn clamp_cursor(&mut self) {
+        let cursor = self.view.cursor;
+        let len = self.document.line_len(cursor.line);
+        if self.mode == Mode::Normal && len > 0 && cursor.col >= len {
+            self.view.cursor.col = self.document.line(cursor.line).prev_boundary(len);
+        }
+    }
+
+    // Move the cursor, or hand the text moved over to the operator waiting for it
+    fn apply_motion(&mut self, motion: Motion, count: Option<usize>) -> Result<(), Error> {
+        let Some((operator, operator_count)) = self.pending_operator.take() else {
+            self.document.seal_undo_step();
+            self.view.cursor = match motion {
+                // Up and down go by screen rows over wrapped lines, operators still take whole lines
+                Motion::Up | Motion::Down if self.view.soft_wrap.enabled => self
+                    .view
+                    .row_target(&self.document, self.view.cursor, motion == Motion::Down, count.unwrap_or(1))
+                    .unwrap_or(self.view.cursor),
+                _ => motion.target(&self.document, self.view.cursor, count),
+            };
+            return Ok(());
+        };
+        // Counts on both sides multiply, 2d3w deletes six words
+        let count = match (operator_count, count) {
+            (Some(first), Some(second)) => Some(first.saturating_mul(second)),
+            (first, second) => first.or(second),
+        };
+        let from = self.view.cursor;
+        // cw changes up to the end of the word like ce, keeping the blank after it
+        let on_word = self.document.text_range(from, Motion::Right.target(&self.document, from, None));
+        let motion = if operator == Operator::Change
+            && motion == Motion::WordForward
+            && on_word.chars().next().is_some_and(|c| !c.is_whitespace())
         {
-            match code {
-                Char('q') if *modifiers == KeyModifiers::CONTROL => {
-                    self.should_quit = true;
+            Motion::WordEnd
+        } else {
+            motion
+        };
+        let mut to = motion.target(&self.document, from, count);
+        // Like vim, a word motion that lands before the text of a later line stops at the end
+        // of the line above it, dw on a line's last word keeps the line break
+        if motion == Motion::WordForward && to.line > from.line && to <= self.document.first_non_blank(to.line) {
+            to = Location { line: to.line - 1, col: self.document.line_len(to.line - 1) };
+        }
+        if motion.inclusive() {
+            to.col = self.document.line(to.line).next_boundary(to.col);
+        }
+        let kind = if motion.linewise() { SelectionKind::Line } else { SelectionKind::Char };
+        self.operate(operator, Selection { kind, anchor: from, cursor: to })
+    }
+
+    // Operators act on the selection in visual mode, otherwise they wait for a motion. A doubled
+    // operator (dd, cc, yy) acts on whole lines
+    fn apply_operator(&mut self, operator: Operator, count: Option<usize>) -> Result<(), Error> {
+        if let Some(selection) = self.selection() {
+            return self.operate(operator, selection);
+        }
+        match self.pending_operator.take() {
+            Some((pending, pending_count)) if pending == operator => {
+                let lines = pending_count.unwrap_or(1).saturating_mul(count.unwrap_or(1));
+                let from = self.view.cursor;
+                let last = from.line.saturating_add(lines - 1).min(self.document.last_line());
+                let to = Location { line: last, col: from.col };
+                self.operate(operator, Selection { kind: SelectionKind::Line, anchor: from, cursor: to })
+            }
+            _ => {
+                self.pending_operator = Some((operator, count));
+                Ok(())
+            }
+        }
+    }
+
+    // Run an operator over a selection, from visual mode or from a motion
+    fn operate(&mut self, operator: Operator, selection: Selection) -> Result<(), Error> {
+        let name = self.pending_register.take();
+        match operator {
+            Operator::Yank => {
+                let text = self.selected_text(&selection);
+                self.view.cursor = match selection.kind {
+                    SelectionKind::Line => Location { line: selection.lines().0, col: 0 },
+                    _ => selection.ranges(&self.document)[0].0,
+                };
+                self.yank(name, text);
+                self.leave_selection()
+            }
+            Operator::Delete => {
+                let text = self.delete_selection(&selection);
+                self.store_deleted(name, text);
+                self.leave_selection()
+            }
+            Operator::Change => {
+                let (first, last) = selection.lines();
+                let selection = if selection.kind == SelectionKind::Line {
+                    // Changing lines keeps an empty line to type on
+                    let end = Location { line: last, col: self.document.line_len(last) };
+                    Selection { kind: SelectionKind::Char, anchor: Location { line: first, col: 0 }, cursor: end }
+                } else {
+                    selection
+                };
+                let (left, _) = selection.columns(&self.document);
+                let text = self.delete_selection(&selection);
+                self.store_deleted(name, text);
+                if selection.kind == SelectionKind::Block {
+                    // Typing goes on every line of the block, like in select mode
+                    self.viz_org_cursor_pos = Location { line: first, col: self.document.line(first).x_to_col(left) };
+                    self.view.cursor = Location { line: last, col: self.document.line(last).x_to_col(left) };
+                    self.set_mode(Mode::Select(SelectionKind::Block))
+                } else {
+                    self.set_mode(Mode::Insert)
                 }
-                _ => {
-                    self.terminal.move_cursor(code, modifiers);
-                },
             }
         }
     }
-    fn refresh_screen(&self) -> Result<(), Error> {
-        // self.terminal.hide_cursor()?;
+
+    // Indent or dedent the lines of the selection
+    fn shift_selection(&mut self, indent: bool) -> Result<(), Error> {
+        let Some(selection) = self.selection() else {
+            return Ok(());
+        };
+        let (first, mut last) = selection.lines();
+        // A character selection ending at the start of a line leaves that line alone
+        if selection.kind == SelectionKind::Char && last > first && selection.ranges(&self.document)[0].1.col == 0 {
+            last -= 1;
+        }
+        self.view.cursor = if indent {
+            self.document.indent_lines(first..=last, self.view.cursor)
+        } else {
+            self.document.dedent_lines(first..=last, self.view.cursor)
+        };
+        self.leave_selection()
+    }
+
+    // Replace the selected text with `f` of it, as one undo step
+    fn map_selection(&mut self, f: fn(&str) -> String) -> Result<(), Error> {
+        let Some(selection) = self.selection() else {
+            return Ok(());
+        };
+        let ranges = selection.ranges(&self.document);
+        self.document.begin_undo_group();
+        for &(start, end) in ranges.iter().rev() {
+            let text = f(&self.document.text_range(start, end));
+            self.document.replace_range(start, end, &text, self.view.cursor);
+        }
+        self.document.end_undo_group();
+        self.view.cursor = ranges[0].0;
+        self.leave_selection()
+    }
+
+    fn selected_text(&self, selection: &Selection) -> String {
+        let ranges = selection.ranges(&self.document);
+        let texts: Vec<String> = if selection.kind == SelectionKind::Line {
+            // From the first line's start, not the line break before it that the range may take
+            vec![self.document.text_range(Location { line: selection.lines().0, col: 0 }, ranges[0].1)]
+        } else {
+            ranges.iter().map(|&(start, end)| self.document.text_range(start, end)).collect()
+        };
+        let mut text = texts.join("\n");
+        // Whole lines always end in a line break, even the last line of the document
+        if selection.kind == SelectionKind::Line && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text
+    }
+
+    // Remove the selected text as one undo step, returning it
+    fn delete_selection(&mut self, selection: &Selection) -> String {
+        let text = self.selected_text(selection);
+        let ranges = selection.ranges(&self.document);
+        self.document.begin_undo_group();
+        for &(start, end) in ranges.iter().rev() {
+            self.document.delete_range(start, end, self.view.cursor);
+        }
+        self.document.end_undo_group();
+        self.view.cursor = if selection.kind == SelectionKind::Line {
+            self.document.first_non_blank(selection.lines().0.min(self.document.last_line()))
+        } else {
+            ranges[0].0
+        };
+        text
+    }
+
+    // Replace the block's columns with `c` on every line that reaches them, the block then shrinks
+    // to the column after it so further typing keeps inserting on all lines
+    fn type_in_block(&mut self, selection: &Selection, c: char) {
+        let (left, _) = selection.columns(&self.document);
+        let text = c.to_string();
+        self.document.begin_undo_group();
+        for (start, end) in selection.ranges(&self.document) {
+            if self.document.line(start.line).width() >= left {
+                self.document.replace_range(start, end, &text, self.view.cursor);
+            }
+        }
+        self.document.end_undo_group();
+        let (first, last) = selection.lines();
+        let x = left + Line::new(text).width();
+        self.viz_org_cursor_pos = Location { line: first, col: self.document.line(first).x_to_col(x) };
+        self.view.cursor = Location { line: last, col: self.document.line(last).x_to_col(x) };
+    }
+
+    fn yank(&mut self, name: Option<char>, text: String) {
+        self.copy_to_clipboard(name, &text);
+        self.registers.yank(name.filter(|name| *name != '+'), text);
+    }
+
+    fn store_deleted(&mut self, name: Option<char>, text: String) {
+        self.copy_to_clipboard(name, &text);
+        self.registers.delete(name.filter(|name| *name != '+'), text);
+    }
+
+    // Yanks and cuts into the unnamed register or "+ also reach the system clipboard
+    fn copy_to_clipboard(&mut self, name: Option<char>, text: &str) {
+        if !matches!(name, None | Some('+')) {
+            return;
+        }
+        if let Err(err) = self.terminal.copy_to_clipboard(text) {
+            self.show_error(format!("Failed to send the clipboard to the terminal: {err}"));
+        }
+        if let Err(err) = self.clipboard.copy(text) {
+            self.show_error(format!("Failed to copy with {}: {err}", self.clipboard.name()));
+        }
+    }
+
+    // Insert the picked register at the cursor as one undo step. The first line joins the text
+    // before the cursor and the rest of the cursor's line follows the last one
+    fn paste(&mut self) {
+        if let Some(text) = self.register_text() {
+            self.view.cursor = self.document.insert_text(self.view.cursor, &text);
+        }
+    }
+
+    // Text of the picked register, "+ being the system clipboard. None, saying so, when it's empty
+    fn register_text(&mut self) -> Option<String> {
+        let name = self.pending_register.take();
+        let text = if name == Some('+') {
+            self.clipboard.paste()
+        } else {
+            self.registers.get(name).map(str::to_string)
+        };
+        if text.is_none() {
+            self.show_error(match name {
+                Some(name) => format!("Nothing in register {name}"),
+                None => "Nothing to paste".to_string(),
+            });
+        }
+        text
+    }
+
+    // Put the picked register where the selection was, as one undo step. What was selected goes
+    // to the registers like a delete. Lines replace lines, otherwise the text goes in as it is
+    fn paste_over_selection(&mut self) -> Result<(), Error> {
+        let Some(selection) = self.selection() else {
+            return Ok(());
+        };
+        let Some(mut text) = self.register_text() else {
+            return self.leave_selection();
+        };
+        self.document.begin_undo_group();
+        let deleted = self.delete_selection(&selection);
+        if selection.kind == SelectionKind::Line {
+            let first = selection.lines().0;
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            self.view.cursor = if first > self.document.last_line() {
+                // The lines were the last ones, their line break went with them
+                text.pop();
+                let end = Location { line: self.document.last_line(), col: usize::MAX };
+                let start = self.document.insert_newline(end);
+                self.document.insert_text(start, &text);
+                Location { line: first, col: 0 }
+            } else {
+                self.document.insert_text(Location { line: first, col: 0 }, &text);
+                Location { line: first, col: 0 }
+            };
+        } else {
+            self.view.cursor = self.document.insert_text(self.view.cursor, &text);
+        }
+        self.document.end_undo_group();
+        self.store_deleted(None, deleted);
+        self.leave_selection()
+    }
+
+    // Draw the document, the status bar under it and the message line at the bottom, which the
+    // prompts of the command line and the search take over
+    fn draw(&mut self) -> Result<(), Error> {
+        if self.message.as_ref().is_some_and(|message| message.expired(Instant::now())) {
+            self.message = None;
+        }
+        let selected = self.selection().map(|selection| selection.ranges(&self.document));
+        let confirming = self.substitution.as_ref().and_then(|substitution| substitution.current.as_ref());
+        let matches = if let Some(found) = confirming {
+            vec![(self.document.char_to_location(found.start), self.document.char_to_location(found.end))]
+        } else if self.search.highlight {
+            let first = self.view.scroll_offset.line;
+            self.search.matches(&self.document, first, first + self.view.size.height as usize)
+        } else {
+            Vec::new()
+        };
+        let frame = &mut self.frame;
+        frame.clear();
+        frame.cursor_visible = true;
+        self.view.draw_rows(&self.document, frame, selected.as_deref().unwrap_or_default(), &matches);
+
+        let (width, height) = (self.view.size.width as usize, self.view.size.height as usize);
+        let status = status::status_text(self.mode, self.document.filename(), self.document.is_modified(), self.view.cursor, width);
+        frame.move_cursor_to(Position { x: 0, y: self.view.size.height });
+        frame.set_foreground_color(status::STATUS_FOREGROUND);
+        frame.set_background_color(status::STATUS_BACKGROUND);
+        frame.print(&status);
+        frame.set_foreground_color(Color::Reset);
+        frame.set_background_color(Color::Reset);
+
+        let bottom = Position { x: 0, y: self.view.size.height.saturating_add(1) };
+        frame.move_cursor_to(bottom);
+        if self.mode == Mode::Command {
+            frame.print(&format!(":{}", self.command_line));
+        } else if self.mode == Mode::Search {
+            frame.print(&format!("/{}", self.search.query));
+            if self.search.case_sensitive {
+                // Printed after the query so the cursor can be put back at its end
+                let query_end = Position { x: u16::try_from(Line::new(format!("/{}", self.search.query)).width()).unwrap_or(u16::MAX), ..bottom };
+                frame.print("  [Aa]");
+                frame.move_cursor_to(query_end);
+            }
+        } else {
+            if let (Mode::Confirm, Some(found)) = (self.mode, confirming) {
+                frame.print(&format!("replace with {:?} (y/n/a/q)?", found.replacement));
+            } else if let Some(message) = &self.message {
+                if message.error {
+                    frame.set_foreground_color(status::ERROR_FOREGROUND);
+                }
+                frame.print(&Line::new(message.text.clone()).visible(0, width));
+                frame.set_foreground_color(Color::Reset);
+            }
+            if self.mode == Mode::Palette {
+                self.palette.draw(&self.registry, &self.keymaps, frame, width, height);
+            } else {
+                frame.move_cursor_to(self.view.screen_position(&self.document, self.view.cursor));
+                frame.cursor_visible = self.view.in_view(&self.document, self.view.cursor);
+            }
+        }
+        self.present()
+    }
+
+    // Send the terminal what changed since the last frame, as one synchronized update with the
+    // cursor hidden while it moves around
+    fn present(&mut self) -> Result<(), Error> {
+        self.terminal.begin_synchronized_update()?;
+        self.terminal.hide_cursor()?;
+        let mut at = None; // where the terminal's cursor is, printing moves it along
+        let (mut foreground, mut background) = (None, None);
+        for (position, cell) in self.frame.changes(self.shown.as_ref()) {
+            if at != Some(position) {
+                self.terminal.move_cursor_to(position)?;
+            }
+            if foreground != Some(cell.foreground) {
+                self.terminal.set_foreground_color(cell.foreground)?;
+                foreground = Some(cell.foreground);
+            }
+            if background != Some(cell.background) {
+                self.terminal.set_background_color(cell.background)?;
+                background = Some(cell.background);
+            }
+            self.terminal.print(&cell.symbol)?;
+            at = Some(Position { x: position.x.saturating_add(u16::try_from(Line::new(cell.symbol.clone()).width()).unwrap_or(u16::MAX)), ..position });
+        }
+        if foreground.is_some_and(|color| color != Color::Reset) {
+            self.terminal.set_foreground_color(Color::Reset)?;
+        }
+        if background.is_some_and(|color| color != Color::Reset) {
+            self.terminal.set_background_color(Color::Reset)?;
+        }
+        self.terminal.move_cursor_to(self.frame.cursor)?;
+        if self.frame.cursor_visible {
+            self.terminal.show_cursor()?;
+        }
+        self.terminal.end_synchronized_update()?;
+        self.terminal.execute()?;
+        // What was drawn is now on screen, the old frame is reused for the next one
+        let shown = self.shown.replace(self.frame.clone());
+        if let Some(shown) = shown {
+            self.frame = shown;
+        }
+        Ok(())
+    }
+
+    fn refresh_screen(&mut self) -> Result<(), Error> {
         if self.should_quit {
             self.terminal.clear_screen()?;
+            self.terminal.move_cursor_to(Position { x: 0, y: 0 })?;
             self.terminal.print("Goodbye.\r\n")?;
-        } 
-        self.terminal.show_cursor()?;
-        Terminal::execute()?;
+            self.terminal.execute()?;
+        }
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,140 @@
+use ropey::Rope;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::io::{BufReader, Error, Read, Write};
+use std::ops::Range;
+
+// (line, col) location in the text, col counts chars from the start of the line
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Location {
+    pub line: usize,
+    pub col: usize,
+}
+
+#[cfg(test)]
+impl Location {
+    pub fn new(line: usize, col: usize) -> Self {
+        Self { line, col }
+    }
+}
+
+// Text storage used by the editor, all offsets are char indices unless the name says bytes
+pub trait Buffer {
+    fn len_lines(&self) -> usize;
+    fn len_chars(&self) -> usize;
+
+    // Contents of a line without its line ending, borrowed when the storage has it in one piece
+    fn line(&self, line_idx: usize) -> Cow<'_, str>;
+    // Length of a line in chars without its line ending
+    fn line_len(&self, line_idx: usize) -> usize;
+
+    fn line_to_char(&self, line_idx: usize) -> usize;
+    fn char_to_line(&self, char_idx: usize) -> usize;
+    fn char_to_byte(&self, char_idx: usize) -> usize;
+    fn byte_to_char(&self, byte_idx: usize) -> usize;
+
+    fn insert(&mut self, char_idx: usize, text: &str);
+    fn remove(&mut self, range: Range<usize>);
+    fn slice(&self, range: Range<usize>) -> String;
+
+    fn write_to(&self, writer: &mut dyn Write) -> Result<(), Error>;
+
+    // Char index of a location, clamping the column to the line
+    fn location_to_char(&self, location: Location) -> usize {
+        let line = location.line.min(self.len_lines().saturating_sub(1));
+        self.line_to_char(line) + location.col.min(self.line_len(line))
+    }
+
+    fn char_to_location(&self, char_idx: usize) -> Location {
+        let line = self.char_to_line(char_idx);
+        Location { line, col: char_idx - self.line_to_char(line) }
+    }
+}
+
+// Rope backed buffer, edits and line lookups are O(log n) in the size of the text
+pub struct RopeBuffer {
+    rope: Rope,
+}
+
+impl RopeBuffer {
+    pub fn new() -> Self {
+        Self { rope: Rope::new() }
+    }
+
+    // Build the rope straight from a reader without holding a second copy of the text
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, Error> {
+        Ok(Self { rope: Rope::from_reader(BufReader::new(reader))? })
+    }
+}
+
+impl Default for RopeBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<&str> for RopeBuffer {
+    fn from(text: &str) -> Self {
+        Self { rope: Rope::from_str(text) }
+    }
+}
+
+impl Buffer for RopeBuffer {
+    fn len_lines(&self) -> usize {
+        self.rope.len_lines()
+    }
+
+    fn len_chars(&self) -> usize {
+        self.rope.len_chars()
+    }
+
+    fn line(&self, line_idx: usize) -> Cow<'_, str> {
+        let slice = self.rope.line(line_idx);
+        let slice = slice.slice(..self.line_len(line_idx));
+        slice.as_str().map_or_else(|| Cow::Owned(slice.to_string()), Cow::Borrowed)
+    }
+
+    fn line_len(&self, line_idx: usize) -> usize {
+        let line = self.rope.line(line_idx);
+        let mut len = line.len_chars();
+        while len > 0 && matches!(line.char(len - 1), '\n' | '\r') {
+            len -= 1;
+        }
+        len
+    }
+
+    fn line_to_char(&self, line_idx: usize) -> usize {
+        self.rope.line_to_char(line_idx)
+    }
+
+    fn char_to_line(&self, char_idx: usize) -> usize {
+        self.rope.char_to_line(char_idx)
+    }
+
+    fn char_to_byte(&self, char_idx: usize) -> usize {
+        self.rope.char_to_byte(char_idx)
+    }
+
+    fn byte_to_char(&self, byte_idx: usize) -> usize {
+        self.rope.byte_to_char(byte_idx)
+    }
+
+    fn insert(&mut self, char_idx: usize, text: &str) {
+        self.rope.insert(char_idx, text);
+    }
+
+    fn remove(&mut self, range: Range<usize>) {
+        self.rope.remove(range);
+    }
+
+    fn slice(&self, range: Range<usize>) -> String {
+        self.rope.slice(range).to_string()
+    }
+
+    fn write_to(&self, writer: &mut dyn Write) -> Result<(), Error> {
+        for chunk in self.rope.chunks() {
+            writer.write_all(chunk.as_bytes())?;
+        }
+        Ok(())
+    }
+}
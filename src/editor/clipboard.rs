@@ -0,0 +1,139 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::env;
+use std::io::{Error, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// Where copied text goes besides the terminal's own OSC 52 clipboard
+pub trait ClipboardProvider {
+    fn name(&self) -> &'static str;
+    fn copy(&mut self, text: &str) -> Result<(), Error>;
+    // None when the clipboard is empty or unreadable
+    fn paste(&mut self) -> Option<String>;
+}
+
+// Escape sequence asking the terminal to put `text` on the system clipboard. Terminals pass it
+// through SSH and tmux (with set-clipboard on), so it works where no local tool can
+pub fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", STANDARD.encode(text))
+}
+
+// Pick the first clipboard tool that is installed for the running display server, keeping
+// copies inside the editor when there is none
+pub fn detect() -> Box<dyn ClipboardProvider> {
+    let tools: &[Tool] = if env::var_os("WAYLAND_DISPLAY").is_some() {
+        &[WL_CLIPBOARD]
+    } else if env::var_os("DISPLAY").is_some() {
+        &[XCLIP, XSEL]
+    } else {
+        &[]
+    };
+    tools
+        .iter()
+        .find(|tool| on_path(tool.copy[0]))
+        .map_or_else(|| Box::new(Internal::default()) as Box<dyn ClipboardProvider>, |tool| Box::new(*tool))
+}
+
+fn on_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|paths| env::split_paths(&paths).any(|dir| is_executable(&dir.join(program))))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata().is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+// No execute bit to check elsewhere
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+// An external program reading the text on stdin to copy and printing it to paste
+#[derive(Copy, Clone)]
+struct Tool {
+    name: &'static str,
+    copy: &'static [&'static str],
+    paste: &'static [&'static str],
+}
+
+const WL_CLIPBOARD: Tool = Tool { name: "wl-copy", copy: &["wl-copy"], paste: &["wl-paste", "--no-newline"] };
+const XCLIP: Tool =
+    Tool { name: "xclip", copy: &["xclip", "-selection", "clipboard"], paste: &["xclip", "-selection", "clipboard", "-o"] };
+const XSEL: Tool =
+    Tool { name: "xsel", copy: &["xsel", "--clipboard", "--input"], paste: &["xsel", "--clipboard", "--output"] };
+
+impl ClipboardProvider for Tool {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn copy(&mut self, text: &str) -> Result<(), Error> {
+        let mut child = Command::new(self.copy[0])
+            .args(&self.copy[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        // xclip and wl-copy fork to keep serving the selection, waiting only reaps the parent
+        let status = child.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(Error::other(format!("{} exited with {status}", self.name)))
+        }
+    }
+
+    fn paste(&mut self) -> Option<String> {
+        let output = Command::new(self.paste[0]).args(&self.paste[1..]).stderr(Stdio::null()).output().ok()?;
+        if output.status.success() {
+            String::from_utf8(output.stdout).ok()
+        } else {
+            None
+        }
+    }
+}
+
+// Clipboard private to the editor, used when no tool is available
+#[derive(Default)]
+pub struct Internal {
+    text: Option<String>,
+}
+
+impl ClipboardProvider for Internal {
+    fn name(&self) -> &'static str {
+        "internal"
+    }
+
+    fn copy(&mut self, text: &str) -> Result<(), Error> {
+        self.text = Some(text.to_string());
+        Ok(())
+    }
+
+    fn paste(&mut self) -> Option<String> {
+        self.text.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn osc52_encodes_the_text() {
+        assert_eq!(osc52("héllo"), "\x1b]52;c;aMOpbGxv\x07");
+    }
+
+    #[test]
+    fn internal_clipboard_round_trips() {
+        let mut clipboard = Internal::default();
+        assert_eq!(clipboard.paste(), None);
+        clipboard.copy("a\nb").unwrap();
+        assert_eq!(clipboard.paste().as_deref(), Some("a\nb"));
+    }
+}
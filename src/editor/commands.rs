@@ -0,0 +1,457 @@
+// Handlers share one signature so they fit in the registry, whether they can fail or not
+#![allow(clippy::unnecessary_wraps)]
+
+use std::io::Error;
+
+use super::buffer::Location;
+use super::mode::Mode;
+use super::motion::{Motion, Operator};
+use super::selection::SelectionKind;
+use super::substitute;
+use super::Editor;
+
+// Handlers behind the registry's commands, each gets the count typed before its key
+
+pub fn quit(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.should_quit = true;
+    Ok(())
+}
+
+pub fn save(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    write_document(editor);
+    Ok(())
+}
+
+// Save, saying how it went. True only when the file and its undo history were both written,
+// a failed save must not take the unsaved buffer or the history down with it
+fn write_document(editor: &mut Editor) -> bool {
+    let name = editor.document.filename().map(|path| path.display().to_string()).unwrap_or_default();
+    match editor.document.save() {
+        Ok(None) => {
+            editor.show_message(format!("\"{name}\" written"));
+            true
+        }
+        Ok(Some(err)) => {
+            editor.show_error(format!("\"{name}\" written, but failed to save undo history: {err}"));
+            false
+        }
+        Err(err) => {
+            editor.show_error(format!("Failed to save: {err}"));
+            false
+        }
+    }
+}
+
+pub fn cycle_line_numbers(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.view.gutter.line_numbers = editor.view.gutter.line_numbers.next();
+    Ok(())
+}
+
+pub fn toggle_soft_wrap(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.view.soft_wrap.enabled = !editor.view.soft_wrap.enabled;
+    Ok(())
+}
+
+pub fn undo(editor: &mut Editor, count: Option<usize>) -> Result<(), Error> {
+    for _ in 0..count.unwrap_or(1) {
+        if let Some(cursor) = editor.document.undo() {
+            editor.view.cursor = cursor;
+        }
+    }
+    Ok(())
+}
+
+pub fn redo(editor: &mut Editor, count: Option<usize>) -> Result<(), Error> {
+    for _ in 0..count.unwrap_or(1) {
+        if let Some(cursor) = editor.document.redo() {
+            editor.view.cursor = cursor;
+        }
+    }
+    Ok(())
+}
+
+// Arrow keys outside normal and visual mode, typing after moving starts a new undo step
+
+pub fn move_up(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.document.seal_undo_step();
+    editor.view.move_up(&editor.document);
+    Ok(())
+}
+
+pub fn move_down(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.document.seal_undo_step();
+    editor.move_down_or_split();
+    Ok(())
+}
+
+pub fn move_left(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.document.seal_undo_step();
+    editor.view.move_left(&editor.document);
+    Ok(())
+}
+
+pub fn move_right(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.document.seal_undo_step();
+    editor.view.move_right(&editor.document);
+    Ok(())
+}
+
+pub fn insert_newline(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.view.cursor = editor.document.insert_newline(editor.view.cursor);
+    Ok(())
+}
+
+pub fn delete_backward(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.view.cursor = editor.document.delete_backward(editor.view.cursor);
+    Ok(())
+}
+
+pub fn paste(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.paste();
+    Ok(())
+}
+
+pub fn paste_over_selection(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.paste_over_selection()
+}
+
+// Like paste but after the cursor, or below the line for text ending in a line break
+pub fn paste_after(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    let Some(text) = editor.register_text() else {
+        return Ok(());
+    };
+    let cursor = editor.view.cursor;
+    let linewise = text.ends_with('\n');
+    editor.view.cursor = if linewise {
+        Location { line: cursor.line + 1, col: 0 }
+    } else {
+        Location { col: editor.document.line(cursor.line).next_boundary(cursor.col), ..cursor }
+    };
+    if linewise && cursor.line == editor.document.last_line() {
+        // There is no next line to paste in front of, start one and drop the extra line break
+        editor.document.begin_undo_group();
+        editor.view.cursor = editor.document.insert_newline(Location { line: cursor.line, col: usize::MAX });
+        editor.view.cursor = editor.document.insert_text(editor.view.cursor, &text);
+        editor.view.cursor = editor.document.delete_backward(editor.view.cursor);
+        editor.document.end_undo_group();
+    } else {
+        editor.view.cursor = editor.document.insert_text(editor.view.cursor, &text);
+    }
+    Ok(())
+}
+
+pub fn pick_register(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.awaiting_register = true;
+    Ok(())
+}
+
+// Mode switches
+
+pub fn normal_mode(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    // Esc in normal mode is also how the last search's highlights go away
+    if editor.mode == Mode::Normal {
+        editor.search.highlight = false;
+    }
+    editor.set_mode(Mode::Normal)
+}
+
+pub fn insert(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.set_mode(Mode::Insert)
+}
+
+pub fn append(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.view.cursor = Motion::Right.target(&editor.document, editor.view.cursor, None);
+    editor.set_mode(Mode::Insert)
+}
+
+pub fn insert_at_line_start(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.view.cursor = editor.document.first_non_blank(editor.view.cursor.line);
+    editor.set_mode(Mode::Insert)
+}
+
+pub fn append_at_line_end(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    let line = editor.view.cursor.line;
+    editor.view.cursor = Location { line, col: editor.document.line_len(line) };
+    editor.set_mode(Mode::Insert)
+}
+
+pub fn open_line_below(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    let line = editor.view.cursor.line;
+    editor.view.cursor = editor.document.insert_newline(Location { line, col: editor.document.line_len(line) });
+    editor.set_mode(Mode::Insert)
+}
+
+pub fn open_line_above(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    let start = Location { line: editor.view.cursor.line, col: 0 };
+    editor.document.insert_newline(start);
+    editor.view.cursor = start;
+    editor.set_mode(Mode::Insert)
+}
+
+pub fn visual_char(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.enter_selection(Mode::Visual(SelectionKind::Char))
+}
+
+pub fn visual_line(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.enter_selection(Mode::Visual(SelectionKind::Line))
+}
+
+pub fn visual_block(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.enter_selection(Mode::Visual(SelectionKind::Block))
+}
+
+pub fn select_char(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.enter_selection(Mode::Select(SelectionKind::Char))
+}
+
+pub fn select_line(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.enter_selection(Mode::Select(SelectionKind::Line))
+}
+
+pub fn select_block(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.enter_selection(Mode::Select(SelectionKind::Block))
+}
+
+pub fn leave_selection(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.leave_selection()
+}
+
+// Operators spelled as a single key
+
+pub fn delete_char(editor: &mut Editor, count: Option<usize>) -> Result<(), Error> {
+    editor.pending_operator = Some((Operator::Delete, None));
+    editor.apply_motion(Motion::Right, count)
+}
+
+pub fn delete_to_line_end(editor: &mut Editor, count: Option<usize>) -> Result<(), Error> {
+    editor.pending_operator = Some((Operator::Delete, None));
+    editor.apply_motion(Motion::LineEnd, count)
+}
+
+pub fn change_to_line_end(editor: &mut Editor, count: Option<usize>) -> Result<(), Error> {
+    editor.pending_operator = Some((Operator::Change, None));
+    editor.apply_motion(Motion::LineEnd, count)
+}
+
+// Operations on the selection
+
+// Copy keeping the selection, unlike y in visual mode
+pub fn yank_selection(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    if let Some(selection) = editor.selection() {
+        let text = editor.selected_text(&selection);
+        let name = editor.pending_register.take();
+        editor.yank(name, text);
+    }
+    Ok(())
+}
+
+pub fn cut_selection(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.apply_operator(Operator::Delete, None)
+}
+
+// Delete without putting the text on the system clipboard
+pub fn delete_selection(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    let Some(selection) = editor.selection() else {
+        return Ok(());
+    };
+    let text = editor.delete_selection(&selection);
+    editor.registers.delete(editor.pending_register.take(), text);
+    editor.leave_selection()
+}
+
+pub fn indent_selection(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.shift_selection(true)
+}
+
+pub fn dedent_selection(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.shift_selection(false)
+}
+
+pub fn uppercase_selection(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.map_selection(str::to_uppercase)
+}
+
+pub fn lowercase_selection(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.map_selection(str::to_lowercase)
+}
+
+// The `:` command line
+
+// From visual mode the command line starts with the selection as its range, like vim
+pub fn command_mode(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.command_line.clear();
+    editor.command_selection = editor.selection();
+    if editor.command_selection.is_some() {
+        editor.command_line.push_str("'<,'>");
+    }
+    editor.set_mode(Mode::Command)
+}
+
+pub fn cancel_command_line(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.set_mode(Mode::Normal)
+}
+
+pub fn command_line_backspace(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    if editor.command_line.pop().is_none() {
+        editor.set_mode(Mode::Normal)?;
+    }
+    Ok(())
+}
+
+pub fn execute_command_line(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    let command = std::mem::take(&mut editor.command_line);
+    editor.set_mode(Mode::Normal)?;
+    if let Some(substitution) = substitute::parse(command.trim()) {
+        return editor.start_substitution(substitution);
+    }
+    match command.trim() {
+        "" => (),
+        "w" => save(editor, None)?,
+        "q" => quit(editor, None)?,
+        "noh" | "nohlsearch" => clear_search_highlight(editor, None)?,
+        "wq" | "x" => {
+            if write_document(editor) {
+                quit(editor, None)?;
+            }
+        }
+        line if line.chars().all(|c| c.is_ascii_digit()) => {
+            let line = line.parse::<usize>().unwrap_or(usize::MAX).max(1);
+            editor.view.cursor = Motion::LastLine.target(&editor.document, editor.view.cursor, Some(line));
+        }
+        help if help.starts_with("help ") => {
+            let name = help["help ".len()..].trim();
+            match editor.registry.get(name) {
+                Some(command) => editor.show_message(format!("{}: {}", command.name, command.description)),
+                None => editor.show_error(format!("No command named {name}")),
+            }
+        }
+        // Any command in the registry can be run by name
+        command => {
+            if !editor.run_command(command, None)? {
+                editor.show_error(format!("Not an editor command: {command}"));
+            }
+        }
+    }
+    Ok(())
+}
+
+// The command palette
+
+pub fn command_palette(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.palette.open(editor.mode);
+    editor.set_mode(Mode::Palette)
+}
+
+pub fn palette_cancel(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.set_mode(editor.palette.return_mode)
+}
+
+pub fn palette_execute(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    let picked = editor.palette.matches(&editor.registry).get(editor.palette.selected).map(|command| command.name);
+    editor.palette.query.clear();
+    editor.palette.selected = 0;
+    editor.set_mode(editor.palette.return_mode)?;
+    if let Some(name) = picked {
+        editor.palette.record(name);
+        editor.run_command(name, None)?;
+    }
+    Ok(())
+}
+
+pub fn palette_backspace(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.palette.query.pop();
+    editor.palette.selected = 0;
+    Ok(())
+}
+
+pub fn palette_next(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    let count = editor.palette.matches(&editor.registry).len();
+    editor.palette.selected = (editor.palette.selected + 1).min(count.saturating_sub(1));
+    Ok(())
+}
+
+pub fn palette_previous(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.palette.selected = editor.palette.selected.saturating_sub(1);
+    Ok(())
+}
+
+// Searching, the prompt moves the cursor to the first match as the query is typed
+
+pub fn search(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.search.open(editor.view.cursor, editor.view.scroll_offset, editor.mode);
+    editor.set_mode(Mode::Search)
+}
+
+pub fn search_accept(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    let empty = editor.search.query.is_empty();
+    editor.search.accept();
+    editor.set_mode(editor.search.return_mode)?;
+    if empty {
+        editor.find_match(editor.search.origin, true, false);
+    }
+    Ok(())
+}
+
+pub fn search_cancel(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.view.cursor = editor.search.origin;
+    editor.view.scroll_offset = editor.search.origin_scroll;
+    editor.search.query.clear();
+    editor.search.highlight = false;
+    editor.set_mode(editor.search.return_mode)
+}
+
+pub fn search_backspace(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    if editor.search.query.pop().is_none() {
+        return search_cancel(editor, None);
+    }
+    editor.update_search();
+    Ok(())
+}
+
+pub fn search_next(editor: &mut Editor, count: Option<usize>) -> Result<(), Error> {
+    editor.search.highlight = true;
+    for _ in 0..count.unwrap_or(1) {
+        editor.find_match(editor.view.cursor, true, false);
+    }
+    Ok(())
+}
+
+pub fn search_previous(editor: &mut Editor, count: Option<usize>) -> Result<(), Error> {
+    editor.search.highlight = true;
+    for _ in 0..count.unwrap_or(1) {
+        editor.find_match(editor.view.cursor, false, false);
+    }
+    Ok(())
+}
+
+pub fn search_toggle_case(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.search.case_sensitive = !editor.search.case_sensitive;
+    editor.update_search();
+    Ok(())
+}
+
+pub fn clear_search_highlight(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.search.highlight = false;
+    Ok(())
+}
+
+// Answers to the confirm prompt of `:s///c`
+
+pub fn confirm_replace(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.confirm_substitution(true, false)
+}
+
+pub fn confirm_skip(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.confirm_substitution(false, false)
+}
+
+pub fn confirm_replace_all(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    editor.confirm_substitution(true, true)
+}
+
+pub fn confirm_quit(editor: &mut Editor, _: Option<usize>) -> Result<(), Error> {
+    match editor.substitution.take() {
+        Some(substitution) => editor.finish_substitution(&substitution),
+        None => editor.set_mode(Mode::Normal),
+    }
+}
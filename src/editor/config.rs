@@ -0,0 +1,112 @@
+use custom_error::custom_error;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::gutter::LineNumbers;
+use super::keymap::{self, Key, Keymaps};
+use super::registry::Registry;
+use super::view::SIDE_SCROLL_MARGIN;
+
+custom_error! {pub ConfigError
+    Read{path: PathBuf, source: std::io::Error} = @{format!("could not read {}: {source}", path.display())},
+    Parse{path: PathBuf, source: toml::de::Error} = @{format!("invalid config {}: {source}", path.display())},
+    UnknownMode{mode: String} = "[keys.{mode}]: no such mode, expected normal, insert, visual, select, command, palette, search or confirm",
+    InvalidChord{mode: String, chord: String, reason: String} = "[keys.{mode}] \"{chord}\": {reason}",
+    UnknownCommand{mode: String, chord: String, command: String} = "[keys.{mode}] \"{chord}\": no command named `{command}`",
+    Conflict{mode: String, chord: String, other: String} = "[keys.{mode}] \"{chord}\" conflicts with \"{other}\", one of them could never be typed",
+}
+
+// config.toml as written by the user, editor settings and key bindings per mode:
+//
+//   [editor]
+//   line-numbers = "relative"
+//   side-scroll-margin = 8
+//   soft-wrap = true
+//   wrap-indicator = "↪ "
+//
+//   [keys.normal]
+//   "g g" = "first-line"
+//   "Ctrl+K Ctrl+C" = "copy-selection"
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    editor: Settings,
+    #[serde(default)]
+    keys: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+// The [editor] table, anything left out keeps its default
+#[derive(Deserialize, Debug)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Settings {
+    pub line_numbers: LineNumbers,
+    pub side_scroll_margin: usize,
+    pub soft_wrap: bool,
+    pub wrap_indicator: String,
+    pub wrap_at_words: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            line_numbers: LineNumbers::default(),
+            side_scroll_margin: SIDE_SCROLL_MARGIN,
+            soft_wrap: false,
+            wrap_indicator: String::new(),
+            wrap_at_words: true,
+        }
+    }
+}
+
+pub struct Config {
+    pub settings: Settings,
+    pub keymaps: Keymaps,
+}
+
+// $XDG_CONFIG_HOME/crab/config.toml, falling back to ~/.config/crab/config.toml
+pub fn default_path() -> Option<PathBuf> {
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config.join("crab").join("config.toml"))
+}
+
+// The settings, and the default keymaps with the config's bindings on top. A missing file
+// changes nothing
+pub fn load(path: &Path, registry: &Registry) -> Result<Config, ConfigError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(source) => return Err(ConfigError::Read { path: path.to_path_buf(), source }),
+    };
+    let config: ConfigFile =
+        toml::from_str(&text).map_err(|source| ConfigError::Parse { path: path.to_path_buf(), source })?;
+    let mut keymaps = Keymaps::default();
+    for (mode, bindings) in &config.keys {
+        let keymap = keymaps.by_name_mut(mode).ok_or_else(|| ConfigError::UnknownMode { mode: mode.clone() })?;
+        let mut bound: Vec<(&str, Vec<Key>)> = Vec::new();
+        for (chord, command) in bindings {
+            let keys = keymap::parse_chord(chord).map_err(|reason| ConfigError::InvalidChord {
+                mode: mode.clone(),
+                chord: chord.clone(),
+                reason,
+            })?;
+            // Defaults give way to the config, but two bindings in the config must not shadow each other
+            if let Some((other, _)) = bound.iter().find(|(_, other)| other.starts_with(&keys) || keys.starts_with(other)) {
+                return Err(ConfigError::Conflict { mode: mode.clone(), chord: chord.clone(), other: (*other).to_string() });
+            }
+            let command = registry.get(command).ok_or_else(|| ConfigError::UnknownCommand {
+                mode: mode.clone(),
+                chord: chord.clone(),
+                command: command.clone(),
+            })?;
+            keymap.bind(&keys, command.name);
+            bound.push((chord, keys));
+        }
+    }
+    Ok(Config { settings: config.editor, keymaps })
+}
@@ -0,0 +1,413 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::buffer::{Buffer, Location, RopeBuffer};
+use super::file_io;
+use super::history::{Edit, History};
+use super::line::Line;
+use super::undo_store;
+
+const INDENT: &str = "    ";
+
+// Revisions are unique across documents, so a cache can't mistake one document for another
+static NEXT_REVISION: AtomicU64 = AtomicU64::new(1);
+
+fn next_revision() -> u64 {
+    NEXT_REVISION.fetch_add(1, Ordering::Relaxed)
+}
+
+// Line changes remembered for caches catching up, one further behind starts over
+const LINE_CHANGES_KEPT: usize = 64;
+
+// Lines `line..=line + removed` as they were at `revision` became `line..=line + inserted`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LineChange {
+    pub revision: u64,
+    pub line: usize,
+    pub removed: usize,
+    pub inserted: usize,
+}
+
+// The text being edited and the file it belongs to, independent of any screen
+#[derive(Default)]
+pub struct Document {
+    buffer: RopeBuffer,
+    filename: Option<PathBuf>,
+    history: History,
+    undo_dir: Option<PathBuf>, // where the undo history is kept between sessions
+    modified: bool,            // changed since it was opened or saved
+    revision: u64,             // new with every change, tells caches of the layout they are stale
+    line_changes: VecDeque<LineChange>, // the latest changes, oldest first
+    haystack: RefCell<Option<(u64, Rc<str>)>>, // the whole text for searching, and its revision
+}
+
+impl Document {
+    // Load a file into a document, a missing file starts an empty document that is created on save.
+    // The undo history from an earlier session is restored if the file is unchanged since.
+    pub fn open(path: &Path, undo_dir: Option<&Path>) -> Result<Self, Error> {
+        let buffer = match file_io::open_file(path)? {
+            Some(file) => RopeBuffer::from_reader(file)?,
+            None => RopeBuffer::new(),
+        };
+        let history = undo_dir
+            .and_then(|dir| {
+                let history_file = undo_store::history_path(dir, path);
+                undo_store::load(&history_file, path, undo_store::content_hash(&buffer))
+            })
+            .unwrap_or_default();
+        Ok(Self {
+            buffer,
+            filename: Some(path.to_path_buf()),
+            history,
+            undo_dir: undo_dir.map(Path::to_path_buf),
+            modified: false,
+            revision: next_revision(),
+            line_changes: VecDeque::new(),
+            haystack: RefCell::default(),
+        })
+    }
+
+    // Write the document back to its file atomically, along with its undo history. Returns the
+    // error from saving the history when only that failed
+    pub fn save(&mut self) -> Result<Option<Error>, Error> {
+        let Some(path) = &self.filename else {
+            return Err(Error::new(ErrorKind::InvalidInput, "No file name to save to"));
+        };
+        file_io::write_atomic(path, |writer| self.buffer.write_to(writer))?;
+
+        // The file itself is safe on disk, losing the history isn't worth failing the save
+        let history_error = self.undo_dir.as_ref().and_then(|dir| {
+            let history_file = undo_store::history_path(dir, path);
+            let content_hash = undo_store::content_hash(&self.buffer);
+            undo_store::store(&history_file, path, content_hash, &self.history).err()
+        });
+        self.modified = false;
+        Ok(history_error)
+    }
+
+    pub fn filename(&self) -> Option<&Path> {
+        self.filename.as_deref()
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    // Changes made since `revision` in the order they were made, None when they are no longer
+    // all remembered or `revision` was never this document's
+    pub fn line_changes_since(&self, revision: u64) -> Option<impl Iterator<Item = &LineChange>> {
+        let first = if revision == self.revision {
+            self.line_changes.len()
+        } else {
+            self.line_changes.iter().position(|change| change.revision == revision)?
+        };
+        Some(self.line_changes.range(first..))
+    }
+
+    // Apply an edit to the buffer under a new revision, noting the lines it touched so caches of
+    // the layout can keep the rest
+    fn change(&mut self, edit: &Edit) {
+        let at = match edit {
+            Edit::Insert { at, .. } | Edit::Delete { at, .. } => *at,
+        };
+        let (line, len_lines) = (self.buffer.char_to_line(at), self.buffer.len_lines());
+        edit.apply(&mut self.buffer);
+        if self.line_changes.len() == LINE_CHANGES_KEPT {
+            self.line_changes.pop_front();
+        }
+        self.line_changes.push_back(LineChange {
+            revision: self.revision,
+            line,
+            removed: len_lines.saturating_sub(self.buffer.len_lines()),
+            inserted: self.buffer.len_lines().saturating_sub(len_lines),
+        });
+        self.modified = true;
+        self.revision = next_revision();
+    }
+
+    pub fn len_lines(&self) -> usize {
+        self.buffer.len_lines()
+    }
+
+    pub fn last_line(&self) -> usize {
+        self.len_lines().saturating_sub(1)
+    }
+
+    pub fn line(&self, line_idx: usize) -> Line<'_> {
+        Line::new(self.buffer.line(line_idx))
+    }
+
+    pub fn line_len(&self, line_idx: usize) -> usize {
+        self.buffer.line_len(line_idx)
+    }
+
+    // Apply an edit and record it for undo, returning the location right after it
+    fn apply(&mut self, edit: Edit, cursor_before: Location, typing: bool) -> Location {
+        self.change(&edit);
+        let cursor_after = match &edit {
+            Edit::Insert { at, text } => self.buffer.char_to_location(at + text.chars().count()),
+            Edit::Delete { at, .. } => self.buffer.char_to_location(*at),
+        };
+        self.history.record(edit, cursor_before, cursor_after, typing);
+        cursor_after
+    }
+
+    // Insert a typed char, consecutive typing is undone as one step
+    pub fn insert_char(&mut self, at: Location, c: char) -> Location {
+        let edit = Edit::Insert { at: self.buffer.location_to_char(at), text: c.to_string() };
+        self.apply(edit, at, true)
+    }
+
+    // Insert text that may span several lines, returning the location right after it
+    pub fn insert_text(&mut self, at: Location, text: &str) -> Location {
+        let edit = Edit::Insert { at: self.buffer.location_to_char(at), text: text.to_string() };
+        self.apply(edit, at, false)
+    }
+
+    // Split the line at the location, returning where the new line starts
+    pub fn insert_newline(&mut self, at: Location) -> Location {
+        self.insert_text(at, "\n")
+    }
+
+    // Delete the grapheme before the location, joining lines at the start of a line
+    pub fn delete_backward(&mut self, at: Location) -> Location {
+        let start = if at.col > 0 {
+            Location { line: at.line, col: self.line(at.line).prev_boundary(at.col) }
+        } else if at.line > 0 {
+            // Join with the previous line, dropping its line ending (\n or \r\n)
+            Location { line: at.line - 1, col: self.line_len(at.line - 1) }
+        } else {
+            return at;
+        };
+        self.delete_range(start, at, at)
+    }
+
+    // Delete the text between two locations, returning where it started
+    pub fn delete_range(&mut self, from: Location, to: Location, cursor_before: Location) -> Location {
+        let start = self.buffer.location_to_char(from);
+        let end = self.buffer.location_to_char(to);
+        if start >= end {
+            return from;
+        }
+        let edit = Edit::Delete { at: start, text: self.buffer.slice(start..end) };
+        self.apply(edit, cursor_before, false)
+    }
+
+    // Replace the text between two locations as one undo step, returning the end of the new text
+    pub fn replace_range(&mut self, from: Location, to: Location, text: &str, cursor_before: Location) -> Location {
+        self.history.begin_group();
+        self.delete_range(from, to, cursor_before);
+        let end = self.insert_text(from, text);
+        self.history.end_group();
+        end
+    }
+
+    // Put one level of indentation in front of each line, returning where the cursor moves
+    pub fn indent_lines(&mut self, lines: RangeInclusive<usize>, cursor_before: Location) -> Location {
+        self.history.begin_group();
+        for line in lines.clone() {
+            if self.line_len(line) > 0 {
+                let edit = Edit::Insert { at: self.buffer.line_to_char(line), text: INDENT.to_string() };
+                self.apply(edit, cursor_before, false);
+            }
+        }
+        self.history.end_group();
+        self.first_non_blank(*lines.start())
+    }
+
+    // Remove one level of indentation, a tab or up to INDENT's worth of spaces, from each line
+    pub fn dedent_lines(&mut self, lines: RangeInclusive<usize>, cursor_before: Location) -> Location {
+        self.history.begin_group();
+        for line in lines.clone() {
+            let text = self.line(line).to_string();
+            let len = if text.starts_with('\t') {
+                1
+            } else {
+                text.chars().take(INDENT.len()).take_while(|c| *c == ' ').count()
+            };
+            self.delete_range(Location { line, col: 0 }, Location { line, col: len }, cursor_before);
+        }
+        self.history.end_group();
+        self.first_non_blank(*lines.start())
+    }
+
+    // Location of the first char on a line that isn't whitespace
+    pub fn first_non_blank(&self, line: usize) -> Location {
+        let col = self.line(line).to_string().chars().take_while(|c| c.is_whitespace()).count();
+        Location { line, col }
+    }
+
+    // Revert the last step, returning the cursor from before it
+    pub fn undo(&mut self) -> Option<Location> {
+        let transaction = self.history.undo()?;
+        for edit in transaction.edits.iter().rev() {
+            self.change(&edit.inverse());
+        }
+        Some(transaction.cursor_before)
+    }
+
+    // Reapply the last undone step, returning the cursor from after it
+    pub fn redo(&mut self) -> Option<Location> {
+        let transaction = self.history.redo()?;
+        for edit in &transaction.edits {
+            self.change(edit);
+        }
+        Some(transaction.cursor_after)
+    }
+
+    // Edits made until end_undo_group are undone as one step
+    pub fn begin_undo_group(&mut self) {
+        self.history.begin_group();
+    }
+
+    pub fn end_undo_group(&mut self) {
+        self.history.end_group();
+    }
+
+    // Start a new undo step for the next typed char
+    pub fn seal_undo_step(&mut self) {
+        self.history.seal();
+    }
+
+    // The whole text as it is written to disk, for searches that span lines
+    pub fn text(&self) -> String {
+        self.buffer.slice(0..self.buffer.len_chars())
+    }
+
+    // The whole text in one piece for regex searches, copied out of the buffer once per revision
+    // however often it is searched
+    pub fn haystack(&self) -> Rc<str> {
+        let mut haystack = self.haystack.borrow_mut();
+        match &*haystack {
+            Some((revision, text)) if *revision == self.revision => Rc::clone(text),
+            _ => {
+                let text: Rc<str> = self.text().into();
+                *haystack = Some((self.revision, Rc::clone(&text)));
+                text
+            }
+        }
+    }
+
+    pub fn len_chars(&self) -> usize {
+        self.buffer.len_chars()
+    }
+
+    pub fn location_to_char(&self, location: Location) -> usize {
+        self.buffer.location_to_char(location)
+    }
+
+    pub fn char_to_location(&self, char_idx: usize) -> Location {
+        self.buffer.char_to_location(char_idx)
+    }
+
+    // Offsets into `text()` and `haystack()` are bytes, the buffer counts chars
+    pub fn byte_to_char(&self, byte_idx: usize) -> usize {
+        self.buffer.byte_to_char(byte_idx)
+    }
+
+    pub fn char_to_byte(&self, char_idx: usize) -> usize {
+        self.buffer.char_to_byte(char_idx)
+    }
+
+    // Text between two locations, `from` must not come after `to`
+    pub fn text_range(&self, from: Location, to: Location) -> String {
+        self.buffer.slice(self.buffer.location_to_char(from)..self.buffer.location_to_char(to))
+    }
+}
+
+// Fixture for tests across the editor, a document holding `text` with no history
+#[cfg(test)]
+impl Document {
+    pub fn from_text(text: &str) -> Self {
+        Self { buffer: RopeBuffer::from(text), ..Self::default() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(document: &Document) -> String {
+        document.buffer.slice(0..document.buffer.len_chars())
+    }
+
+    #[test]
+    fn insert_char_advances_location() {
+        let mut doc = Document::from_text("helo");
+        let at = doc.insert_char(Location::new(0, 3), 'l');
+        assert_eq!(text(&doc), "hello");
+        assert_eq!(at, Location::new(0, 4));
+    }
+
+    #[test]
+    fn insert_newline_splits_line() {
+        let mut doc = Document::from_text("hello world");
+        let at = doc.insert_newline(Location::new(0, 5));
+        assert_eq!(text(&doc), "hello\n world");
+        assert_eq!(at, Location::new(1, 0));
+    }
+
+    #[test]
+    fn delete_backward_joins_lines() {
+        let mut doc = Document::from_text("foo\r\nbar");
+        let at = doc.delete_backward(Location::new(1, 0));
+        assert_eq!(text(&doc), "foobar");
+        assert_eq!(at, Location::new(0, 3));
+        assert_eq!(doc.delete_backward(Location::default()), Location::default());
+    }
+
+    #[test]
+    fn delete_backward_removes_whole_grapheme() {
+        let mut doc = Document::from_text("ae\u{301}b");
+        let at = doc.delete_backward(Location::new(0, 3));
+        assert_eq!(text(&doc), "ab");
+        assert_eq!(at, Location::new(0, 1));
+    }
+
+    #[test]
+    fn undo_and_redo_restore_text_and_cursor() {
+        let mut doc = Document::from_text("ab");
+        let mut at = Location::new(0, 2);
+        for c in "cd".chars() {
+            at = doc.insert_char(at, c);
+        }
+        at = doc.insert_newline(at);
+        doc.insert_char(at, 'e');
+        assert_eq!(text(&doc), "abcd\ne");
+
+        assert_eq!(doc.undo(), Some(Location::new(1, 0)));
+        assert_eq!(doc.undo(), Some(Location::new(0, 4)));
+        assert_eq!(doc.undo(), Some(Location::new(0, 2)));
+        assert_eq!(text(&doc), "ab");
+        assert_eq!(doc.undo(), None);
+
+        assert_eq!(doc.redo(), Some(Location::new(0, 4)));
+        assert_eq!(text(&doc), "abcd");
+    }
+
+    #[test]
+    fn text_range_spans_lines() {
+        let doc = Document::from_text("one\ntwo\nthree");
+        let copied = doc.text_range(Location::new(0, 1), Location::new(2, 2));
+        assert_eq!(copied, "ne\ntwo\nth");
+    }
+
+    #[test]
+    fn dedent_removes_one_level() {
+        let mut doc = Document::from_text("\t\tx\n      y\n  z");
+        let at = doc.dedent_lines(0..=2, Location::default());
+        assert_eq!(doc.text_range(Location::default(), Location::new(2, 1)), "\tx\n  y\nz");
+        assert_eq!(at, Location::new(0, 1));
+        assert_eq!(doc.undo(), Some(Location::default()));
+        assert_eq!(doc.line(1).to_string(), "      y");
+    }
+}
@@ -0,0 +1,60 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Error, ErrorKind, IntoInnerError, Write};
+use std::path::{Path, PathBuf};
+
+// Open a file for reading, returning None if it doesn't exist yet (a new file)
+pub fn open_file(path: &Path) -> Result<Option<File>, Error> {
+    match File::open(path) {
+        Ok(file) => Ok(Some(file)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+// Write the contents to a temp file next to `path`, sync it and rename it over the target,
+// so a crash mid-save never leaves a truncated file behind
+pub fn write_atomic<F>(path: &Path, write_contents: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut dyn Write) -> Result<(), Error>,
+{
+    let tmp_path = temp_path_for(path);
+    let result = write_and_rename(path, &tmp_path, write_contents);
+    if result.is_err() {
+        // Best effort cleanup, the original error is the one worth reporting
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+fn write_and_rename<F>(path: &Path, tmp_path: &Path, write_contents: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut dyn Write) -> Result<(), Error>,
+{
+    let mut writer = BufWriter::new(File::create(tmp_path)?);
+    write_contents(&mut writer)?;
+    let file = writer.into_inner().map_err(IntoInnerError::into_error)?;
+    file.sync_all()?;
+
+    // Keep the permissions of the file we are replacing
+    if let Ok(metadata) = fs::metadata(path) {
+        fs::set_permissions(tmp_path, metadata.permissions())?;
+    }
+
+    fs::rename(tmp_path, path)?;
+
+    // Sync the directory so the rename itself survives a crash
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+// The temp file lives in the same directory so the rename stays on one filesystem
+fn temp_path_for(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map_or_else(|| "untitled".into(), |name| name.to_string_lossy().into_owned());
+    path.with_file_name(format!(".{file_name}.crab-tmp-{}", std::process::id()))
+}
@@ -0,0 +1,152 @@
+use crossterm::style::Color;
+use serde::Deserialize;
+
+use super::terminal::Frame;
+
+const MIN_NUMBER_WIDTH: usize = 3;
+const NUMBER_FOREGROUND: Color = Color::DarkGrey;
+const CURRENT_NUMBER_FOREGROUND: Color = Color::Yellow;
+
+// How the gutter numbers lines, set with `line-numbers` under [editor] in the config
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineNumbers {
+    #[default]
+    Off,
+    Absolute,
+    Relative, // distance from the cursor's line, which shows 0
+    Hybrid,   // relative, but the cursor's line shows its own number
+}
+
+impl LineNumbers {
+    #[must_use]
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Absolute,
+            Self::Absolute => Self::Relative,
+            Self::Relative => Self::Hybrid,
+            Self::Hybrid => Self::Off,
+        }
+    }
+}
+
+// A marker in the sign column. Features put theirs under a group of their own, so they can
+// clear them without touching the others'
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sign {
+    pub group: String,
+    pub line: usize,
+    pub symbol: char,
+    pub color: Color,
+}
+
+// Columns left of the text: a sign column while there are signs, then the line numbers
+#[derive(Default)]
+pub struct Gutter {
+    pub line_numbers: LineNumbers,
+    signs: Vec<Sign>, // a later sign on the same line is drawn over an earlier one
+}
+
+impl Gutter {
+    pub fn set_sign(&mut self, sign: Sign) {
+        self.signs.retain(|other| other.group != sign.group || other.line != sign.line);
+        self.signs.push(sign);
+    }
+
+    pub fn clear_signs(&mut self, group: &str) {
+        self.signs.retain(|sign| sign.group != group);
+    }
+
+    fn sign(&self, line: usize) -> Option<&Sign> {
+        self.signs.iter().rev().find(|sign| sign.line == line)
+    }
+
+    fn sign_width(&self) -> usize {
+        if self.signs.is_empty() {
+            0
+        } else {
+            2
+        }
+    }
+
+    // Digits for the biggest number shown, 3 at least so the gutter doesn't jump around while
+    // a short file grows
+    fn number_width(&self, len_lines: usize) -> usize {
+        if self.line_numbers == LineNumbers::Off {
+            0
+        } else {
+            len_lines.to_string().len().max(MIN_NUMBER_WIDTH)
+        }
+    }
+
+    // Screen columns taken from the text, with a space between the numbers and the text
+    pub fn width(&self, len_lines: usize) -> usize {
+        match self.number_width(len_lines) {
+            0 => self.sign_width(),
+            digits => self.sign_width() + digits + 1,
+        }
+    }
+
+    // The number shown next to a line
+    fn number(&self, line: usize, cursor_line: usize) -> Option<usize> {
+        match self.line_numbers {
+            LineNumbers::Off => None,
+            LineNumbers::Relative => Some(line.abs_diff(cursor_line)),
+            LineNumbers::Hybrid if line != cursor_line => Some(line.abs_diff(cursor_line)),
+            LineNumbers::Absolute | LineNumbers::Hybrid => Some(line + 1),
+        }
+    }
+
+    // Print the empty gutter of a row that continues a wrapped line
+    pub fn draw_blank(&self, frame: &mut Frame, len_lines: usize) {
+        frame.print(&" ".repeat(self.width(len_lines)));
+    }
+
+    // Print the gutter of one line at the cursor
+    pub fn draw(&self, frame: &mut Frame, line: usize, cursor_line: usize, len_lines: usize) {
+        if self.sign_width() > 0 {
+            match self.sign(line) {
+                Some(sign) => {
+                    frame.set_foreground_color(sign.color);
+                    frame.print(&format!("{} ", sign.symbol));
+                    frame.set_foreground_color(Color::Reset);
+                }
+                None => frame.print("  "),
+            }
+        }
+        let digits = self.number_width(len_lines);
+        if digits > 0 {
+            let color = if line == cursor_line { CURRENT_NUMBER_FOREGROUND } else { NUMBER_FOREGROUND };
+            let number = self.number(line, cursor_line).map(|n| n.to_string()).unwrap_or_default();
+            frame.set_foreground_color(color);
+            frame.print(&format!("{number:>digits$} "));
+            frame.set_foreground_color(Color::Reset);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn width_follows_the_line_count_and_the_signs() {
+        let mut gutter = Gutter::default();
+        assert_eq!(gutter.width(10), 0);
+        gutter.line_numbers = LineNumbers::Relative;
+        assert_eq!(gutter.width(10), 4);
+        assert_eq!(gutter.width(12_345), 6);
+        gutter.set_sign(Sign { group: "git".to_string(), line: 3, symbol: '+', color: Color::Green });
+        assert_eq!(gutter.width(10), 6);
+        gutter.clear_signs("git");
+        assert_eq!(gutter.width(10), 4);
+    }
+
+    #[test]
+    fn relative_and_hybrid_numbers() {
+        let mut gutter = Gutter { line_numbers: LineNumbers::Relative, ..Gutter::default() };
+        assert_eq!([3, 5, 9].map(|line| gutter.number(line, 5)), [Some(2), Some(0), Some(4)]);
+        gutter.line_numbers = LineNumbers::Hybrid;
+        assert_eq!([3, 5, 9].map(|line| gutter.number(line, 5)), [Some(2), Some(6), Some(4)]);
+    }
+}
@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+
+use super::buffer::{Buffer, Location};
+
+// A single change to the buffer, positions are char indices
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Edit {
+    Insert { at: usize, text: String },
+    Delete { at: usize, text: String },
+}
+
+impl Edit {
+    pub fn inverse(&self) -> Self {
+        match self {
+            Self::Insert { at, text } => Self::Delete { at: *at, text: text.clone() },
+            Self::Delete { at, text } => Self::Insert { at: *at, text: text.clone() },
+        }
+    }
+
+    pub fn apply(&self, buffer: &mut dyn Buffer) {
+        match self {
+            Self::Insert { at, text } => buffer.insert(*at, text),
+            Self::Delete { at, text } => buffer.remove(*at..*at + text.chars().count()),
+        }
+    }
+}
+
+// Edits undone and redone together, with the cursor to restore on either side
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Transaction {
+    pub edits: Vec<Edit>,
+    pub cursor_before: Location,
+    pub cursor_after: Location,
+    #[serde(skip)]
+    typing: bool,
+}
+
+impl Transaction {
+    // Typed chars join the previous step while they continue right where it left off
+    fn continues_typing(&self, edit: &Edit) -> bool {
+        match (self.edits.last(), edit) {
+            (Some(Edit::Insert { at, text }), Edit::Insert { at: next_at, .. }) => {
+                self.typing && at + text.chars().count() == *next_at
+            }
+            _ => false,
+        }
+    }
+}
+
+// Linear undo/redo stacks of transactions
+#[derive(Default)]
+pub struct History {
+    undo_stack: Vec<Transaction>,
+    redo_stack: Vec<Transaction>,
+    group_depth: usize,
+    group_open: bool,
+}
+
+impl History {
+    pub fn from_stacks(undo_stack: Vec<Transaction>, redo_stack: Vec<Transaction>) -> Self {
+        Self { undo_stack, redo_stack, ..Self::default() }
+    }
+
+    pub fn stacks(&self) -> (&[Transaction], &[Transaction]) {
+        (&self.undo_stack, &self.redo_stack)
+    }
+
+    // Record an edit that has already been applied to the buffer
+    pub fn record(&mut self, edit: Edit, cursor_before: Location, cursor_after: Location, typing: bool) {
+        self.redo_stack.clear();
+        if let Some(last) = self.undo_stack.last_mut() {
+            if self.group_open || typing && last.continues_typing(&edit) {
+                last.edits.push(edit);
+                last.cursor_after = cursor_after;
+                return;
+            }
+        }
+        self.undo_stack.push(Transaction { edits: vec![edit], cursor_before, cursor_after, typing });
+        self.group_open = self.group_depth > 0;
+    }
+
+    // Everything recorded until the matching end_group is undone as one step
+    pub fn begin_group(&mut self) {
+        if self.group_depth == 0 {
+            self.group_open = false;
+        }
+        self.group_depth += 1;
+    }
+
+    pub fn end_group(&mut self) {
+        self.group_depth = self.group_depth.saturating_sub(1);
+        if self.group_depth == 0 {
+            self.group_open = false;
+            self.seal();
+        }
+    }
+
+    // Stop the last step from absorbing further typing
+    pub fn seal(&mut self) {
+        if let Some(last) = self.undo_stack.last_mut() {
+            last.typing = false;
+        }
+    }
+
+    // Pop the last step, the caller reverts its edits in reverse order
+    pub fn undo(&mut self) -> Option<Transaction> {
+        let transaction = self.undo_stack.pop()?;
+        self.redo_stack.push(transaction.clone());
+        Some(transaction)
+    }
+
+    // Pop the last undone step, the caller reapplies its edits in order
+    pub fn redo(&mut self) -> Option<Transaction> {
+        let mut transaction = self.redo_stack.pop()?;
+        transaction.typing = false;
+        self.undo_stack.push(transaction.clone());
+        Some(transaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(at: usize, text: &str) -> Edit {
+        Edit::Insert { at, text: text.to_string() }
+    }
+
+    fn cursor(col: usize) -> Location {
+        Location { line: 0, col }
+    }
+
+    #[test]
+    fn consecutive_typing_is_one_step() {
+        let mut history = History::default();
+        history.record(insert(0, "a"), cursor(0), cursor(1), true);
+        history.record(insert(1, "b"), cursor(1), cursor(2), true);
+        history.record(insert(5, "c"), cursor(5), cursor(6), true);
+        let last = history.undo().unwrap();
+        assert_eq!(last.edits, [insert(5, "c")]);
+        let first = history.undo().unwrap();
+        assert_eq!(first.edits, [insert(0, "a"), insert(1, "b")]);
+        assert_eq!((first.cursor_before, first.cursor_after), (cursor(0), cursor(2)));
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn new_edits_clear_redo() {
+        let mut history = History::default();
+        history.record(insert(0, "a"), cursor(0), cursor(1), false);
+        assert!(history.undo().is_some());
+        history.record(insert(0, "b"), cursor(0), cursor(1), false);
+        assert!(history.redo().is_none());
+    }
+
+    #[test]
+    fn groups_collect_every_edit() {
+        let mut history = History::default();
+        history.begin_group();
+        history.record(insert(0, "a"), cursor(0), cursor(1), false);
+        history.record(Edit::Delete { at: 4, text: "x".to_string() }, cursor(1), cursor(4), false);
+        history.end_group();
+        history.record(insert(0, "b"), cursor(0), cursor(1), true);
+        assert_eq!(history.undo().unwrap().edits.len(), 1);
+        assert_eq!(history.undo().unwrap().edits.len(), 2);
+    }
+}
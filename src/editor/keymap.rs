@@ -0,0 +1,491 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+use std::fmt;
+
+use super::mode::Mode;
+
+// A key press, shift is folded into the char it produced so 'V' and Shift+v are the same key
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        let modifiers = match event.code {
+            KeyCode::Char(_) | KeyCode::BackTab => event.modifiers.difference(KeyModifiers::SHIFT),
+            _ => event.modifiers,
+        };
+        Self { code: event.code, modifiers }
+    }
+}
+
+impl Key {
+    pub fn plain_char(self) -> Option<char> {
+        match self.code {
+            KeyCode::Char(c) if self.modifiers.is_empty() => Some(c),
+            _ => None,
+        }
+    }
+}
+
+// Spelled the way the config writes keys, so a shown binding can be pasted into it
+impl fmt::Display for Key {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let ctrl = self.modifiers.contains(KeyModifiers::CONTROL);
+        if ctrl {
+            formatter.write_str("Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            formatter.write_str("Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) || (ctrl && matches!(self.code, KeyCode::Char(c) if c.is_ascii_uppercase())) {
+            formatter.write_str("Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => formatter.write_str("Space"),
+            KeyCode::Char(c) if ctrl => write!(formatter, "{}", c.to_ascii_uppercase()),
+            KeyCode::Char(c) => write!(formatter, "{c}"),
+            KeyCode::F(n) => write!(formatter, "F{n}"),
+            KeyCode::PageUp => formatter.write_str("PageUp"),
+            KeyCode::PageDown => formatter.write_str("PageDown"),
+            KeyCode::BackTab => formatter.write_str("Shift+Tab"),
+            code => write!(formatter, "{code:?}"),
+        }
+    }
+}
+
+// A key sequence for display, keys separated by spaces like `g g`
+pub fn chord_to_string(keys: &[Key]) -> String {
+    keys.iter().map(ToString::to_string).collect::<Vec<_>>().join(" ")
+}
+
+pub enum Lookup {
+    Found(&'static str), // name of the command in the registry
+    Pending, // the keys start a longer sequence
+    Unbound,
+}
+
+// Key sequences bound to command names for one mode
+#[derive(Clone, Default)]
+pub struct Keymap {
+    bindings: HashMap<Vec<Key>, &'static str>,
+}
+
+impl Keymap {
+    // The newest binding wins, sequences it would shadow or be shadowed by are dropped
+    pub fn bind(&mut self, keys: &[Key], command: &'static str) {
+        self.bindings.retain(|bound, _| !bound.starts_with(keys) && !keys.starts_with(bound));
+        self.bindings.insert(keys.to_vec(), command);
+    }
+
+    pub fn lookup(&self, keys: &[Key]) -> Lookup {
+        if let Some(command) = self.bindings.get(keys) {
+            Lookup::Found(command)
+        } else if self.bindings.keys().any(|bound| bound.len() > keys.len() && bound.starts_with(keys)) {
+            Lookup::Pending
+        } else {
+            Lookup::Unbound
+        }
+    }
+}
+
+// One dispatch table per mode
+pub struct Keymaps {
+    normal: Keymap,
+    insert: Keymap,
+    visual: Keymap,
+    select: Keymap,
+    command: Keymap,
+    palette: Keymap,
+    search: Keymap,
+    confirm: Keymap,
+}
+
+impl Keymaps {
+    pub fn for_mode(&self, mode: Mode) -> &Keymap {
+        match mode {
+            Mode::Normal => &self.normal,
+            Mode::Insert => &self.insert,
+            Mode::Visual(_) => &self.visual,
+            Mode::Select(_) => &self.select,
+            Mode::Command => &self.command,
+            Mode::Palette => &self.palette,
+            Mode::Search => &self.search,
+            Mode::Confirm => &self.confirm,
+        }
+    }
+
+    // The table a config section like `[keys.normal]` names
+    pub fn by_name_mut(&mut self, name: &str) -> Option<&mut Keymap> {
+        match name {
+            "normal" => Some(&mut self.normal),
+            "insert" => Some(&mut self.insert),
+            "visual" => Some(&mut self.visual),
+            "select" => Some(&mut self.select),
+            "command" => Some(&mut self.command),
+            "palette" => Some(&mut self.palette),
+            "search" => Some(&mut self.search),
+            "confirm" => Some(&mut self.confirm),
+            _ => None,
+        }
+    }
+
+    // The shortest key sequence that runs a command in a mode, for showing next to its name
+    pub fn binding(&self, mode: Mode, command: &str) -> Option<&[Key]> {
+        self.for_mode(mode)
+            .bindings
+            .iter()
+            .filter(|(_, bound)| **bound == command)
+            .map(|(keys, _)| keys.as_slice())
+            .min_by_key(|keys| (keys.len(), chord_to_string(keys)))
+    }
+}
+
+// A key sequence as written in the config, keys separated by spaces and modifiers joined with `+`,
+// like `g g`, `Ctrl+K Ctrl+C` or `Shift+Tab`
+pub fn parse_chord(chord: &str) -> Result<Vec<Key>, String> {
+    let keys = chord.split_whitespace().map(parse_key).collect::<Result<Vec<_>, _>>()?;
+    if keys.is_empty() {
+        return Err("no keys given".to_string());
+    }
+    Ok(keys)
+}
+
+fn parse_key(text: &str) -> Result<Key, String> {
+    // A trailing `+` is the plus key itself, as in `Ctrl++`
+    let (modifier_part, name) = match text.strip_suffix('+') {
+        Some(rest) if rest.is_empty() || rest.ends_with('+') => (rest.strip_suffix('+').unwrap_or(rest), "+"),
+        _ => text.rsplit_once('+').unwrap_or(("", text)),
+    };
+    let mut modifiers = KeyModifiers::NONE;
+    for modifier in modifier_part.split('+').filter(|modifier| !modifier.is_empty()) {
+        modifiers |= match modifier.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => KeyModifiers::CONTROL,
+            "alt" | "meta" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return Err(format!("unknown modifier `{modifier}`")),
+        };
+    }
+    let code = parse_key_code(name).ok_or_else(|| format!("unknown key `{name}`"))?;
+    Ok(with_modifiers(code, modifiers))
+}
+
+fn parse_key_code(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    let code = match name.to_ascii_lowercase().as_str() {
+        "esc" | "escape" => KeyCode::Esc,
+        "enter" | "return" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" | "ins" => KeyCode::Insert,
+        "space" => KeyCode::Char(' '),
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        function => KeyCode::F(function.strip_prefix('f')?.parse().ok().filter(|n| (1..=24).contains(n))?),
+    };
+    Some(code)
+}
+
+// Fold shift the way `Key::from` does. Terminals report Ctrl+K as Ctrl+k, so the letter's case
+// only matters with Ctrl when Shift is spelled out
+fn with_modifiers(code: KeyCode, modifiers: KeyModifiers) -> Key {
+    let shift = modifiers.contains(KeyModifiers::SHIFT);
+    let code = match code {
+        KeyCode::Char(c) if shift => KeyCode::Char(c.to_ascii_uppercase()),
+        KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => KeyCode::Char(c.to_ascii_lowercase()),
+        KeyCode::Tab if shift => KeyCode::BackTab,
+        code => code,
+    };
+    Key::from(KeyEvent::new(code, modifiers))
+}
+
+pub fn key(c: char) -> Key {
+    Key { code: KeyCode::Char(c), modifiers: KeyModifiers::NONE }
+}
+
+pub fn ctrl(c: char) -> Key {
+    Key { code: KeyCode::Char(c), modifiers: KeyModifiers::CONTROL }
+}
+
+pub fn alt(c: char) -> Key {
+    Key { code: KeyCode::Char(c), modifiers: KeyModifiers::ALT }
+}
+
+pub fn code(code: KeyCode) -> Key {
+    Key { code, modifiers: KeyModifiers::NONE }
+}
+
+impl Default for Keymaps {
+    fn default() -> Self {
+        let global = global_bindings();
+        let motions = motion_bindings();
+        let selection = selection_bindings(&global);
+        Self {
+            normal: normal_bindings(&global, &motions),
+            insert: insert_bindings(&global),
+            visual: visual_bindings(&selection, &motions),
+            select: select_bindings(selection),
+            command: command_bindings(global.clone()),
+            palette: palette_bindings(global.clone()),
+            search: search_bindings(global),
+            confirm: confirm_bindings(),
+        }
+    }
+}
+
+// Bound in every mode
+fn global_bindings() -> Keymap {
+    let mut global = Keymap::default();
+    global.bind(&[ctrl('q')], "quit");
+    global.bind(&[ctrl('s')], "save");
+    // Ctrl+Shift+P, terminals that can't tell it apart from Ctrl+P send that instead
+    global.bind(&[ctrl('P')], "command-palette");
+    global.bind(&[ctrl('p')], "command-palette");
+    global.bind(&[ctrl('f')], "search");
+    global
+}
+
+fn motion_bindings() -> Keymap {
+    let mut motions = Keymap::default();
+    for (keys, command) in [
+        (&[key('h')][..], "char-left"),
+        (&[code(KeyCode::Left)], "char-left"),
+        (&[key('l')], "char-right"),
+        (&[code(KeyCode::Right)], "char-right"),
+        (&[key('k')], "line-up"),
+        (&[code(KeyCode::Up)], "line-up"),
+        (&[key('j')], "line-down"),
+        (&[code(KeyCode::Down)], "line-down"),
+        (&[key('w')], "word-forward"),
+        (&[key('b')], "word-backward"),
+        (&[key('e')], "word-end"),
+        (&[key('0')], "line-start"),
+        (&[code(KeyCode::Home)], "line-start"),
+        (&[key('$')], "line-end"),
+        (&[code(KeyCode::End)], "line-end"),
+        (&[key('g'), key('g')], "first-line"),
+        (&[key('G')], "last-line"),
+    ] {
+        motions.bind(keys, command);
+    }
+    motions
+}
+
+fn normal_bindings(global: &Keymap, motions: &Keymap) -> Keymap {
+    let mut normal = Keymap::default();
+    normal.bindings.extend(global.bindings.clone());
+    normal.bindings.extend(motions.bindings.clone());
+    for (keys, command) in [
+        (&[key('d')][..], "delete"),
+        (&[key('c')], "change"),
+        (&[key('y')], "yank"),
+        (&[key('x')], "delete-char"),
+        (&[key('D')], "delete-to-line-end"),
+        (&[key('C')], "change-to-line-end"),
+        (&[key('i')], "insert-mode"),
+        (&[key('a')], "append"),
+        (&[key('I')], "insert-at-line-start"),
+        (&[key('A')], "append-at-line-end"),
+        (&[key('o')], "open-line-below"),
+        (&[key('O')], "open-line-above"),
+        (&[key('p')], "paste-after"),
+        (&[key('P')], "paste"),
+        (&[key('u')], "undo"),
+        (&[ctrl('r')], "redo"),
+        (&[ctrl('z')], "undo"),
+        (&[ctrl('y')], "redo"),
+        (&[key('v')], "visual-mode"),
+        (&[key('V')], "visual-line-mode"),
+        (&[ctrl('v')], "visual-block-mode"),
+        (&[key(':')], "command-mode"),
+        (&[key('"')], "pick-register"),
+        (&[key('/')], "search"),
+        (&[key('n')], "search-next"),
+        (&[key('N')], "search-previous"),
+        (&[code(KeyCode::Esc)], "normal-mode"),
+    ] {
+        normal.bind(keys, command);
+    }
+    normal
+}
+
+fn insert_bindings(global: &Keymap) -> Keymap {
+    let mut insert = Keymap::default();
+    insert.bindings.extend(global.bindings.clone());
+    for (keys, command) in [
+        (&[code(KeyCode::Esc)][..], "normal-mode"),
+        (&[code(KeyCode::Up)], "move-up"),
+        (&[code(KeyCode::Down)], "move-down"),
+        (&[code(KeyCode::Left)], "move-left"),
+        (&[code(KeyCode::Right)], "move-right"),
+        (&[code(KeyCode::Enter)], "insert-newline"),
+        (&[code(KeyCode::Backspace)], "delete-backward"),
+        (&[ctrl('z')], "undo"),
+        (&[ctrl('y')], "redo"),
+        (&[ctrl('b')], "paste"),
+        (&[alt('v')], "select-mode"),
+        (&[alt('V')], "select-line-mode"),
+        (&[ctrl('v')], "select-block-mode"),
+        (&[code(KeyCode::F(3))], "search-next"),
+        (&[Key { code: KeyCode::F(3), modifiers: KeyModifiers::SHIFT }], "search-previous"),
+    ] {
+        insert.bind(keys, command);
+    }
+    insert
+}
+
+// Shortcuts on the selection shared by visual and select mode
+fn selection_bindings(global: &Keymap) -> Keymap {
+    let mut selection = Keymap::default();
+    selection.bindings.extend(global.bindings.clone());
+    for (keys, command) in [
+        (&[ctrl('c')][..], "copy-selection"),
+        (&[ctrl('x')], "cut-selection"),
+        (&[ctrl('b')], "paste"),
+        (&[code(KeyCode::Delete)], "delete-selection"),
+        (&[code(KeyCode::Backspace)], "delete-selection"),
+        (&[code(KeyCode::Tab)], "indent-selection"),
+        (&[code(KeyCode::BackTab)], "dedent-selection"),
+        (&[alt('u')], "uppercase-selection"),
+        (&[alt('l')], "lowercase-selection"),
+        (&[key('"')], "pick-register"),
+        (&[code(KeyCode::Enter)], "leave-selection"),
+        (&[code(KeyCode::Esc)], "leave-selection"),
+    ] {
+        selection.bind(keys, command);
+    }
+    selection
+}
+
+fn visual_bindings(selection: &Keymap, motions: &Keymap) -> Keymap {
+    let mut visual = Keymap::default();
+    visual.bindings.extend(selection.bindings.clone());
+    visual.bindings.extend(motions.bindings.clone());
+    for (keys, command) in [
+        (&[key('d')][..], "delete"),
+        (&[key('x')], "delete"),
+        (&[key('c')], "change"),
+        (&[key('y')], "yank"),
+        (&[key('>')], "indent-selection"),
+        (&[key('<')], "dedent-selection"),
+        (&[key('U')], "uppercase-selection"),
+        (&[key('u')], "lowercase-selection"),
+        (&[key('p')], "paste-over-selection"),
+        (&[key('v')], "visual-mode"),
+        (&[key('V')], "visual-line-mode"),
+        (&[ctrl('v')], "visual-block-mode"),
+        (&[key('/')], "search"),
+        (&[key('n')], "search-next"),
+        (&[key('N')], "search-previous"),
+        (&[key(':')], "command-mode"),
+    ] {
+        visual.bind(keys, command);
+    }
+    visual
+}
+
+// Select mode keeps insert mode's arrow keys, typing replaces the selection
+fn select_bindings(mut select: Keymap) -> Keymap {
+    for (keys, command) in [
+        (&[code(KeyCode::Up)][..], "move-up"),
+        (&[code(KeyCode::Down)], "move-down"),
+        (&[code(KeyCode::Left)], "move-left"),
+        (&[code(KeyCode::Right)], "move-right"),
+        (&[alt('v')], "select-mode"),
+        (&[alt('V')], "select-line-mode"),
+        (&[ctrl('v')], "select-block-mode"),
+    ] {
+        select.bind(keys, command);
+    }
+    select
+}
+
+fn command_bindings(mut command_line: Keymap) -> Keymap {
+    for (keys, command) in [
+        (&[code(KeyCode::Enter)][..], "execute-command-line"),
+        (&[code(KeyCode::Esc)], "cancel-command-line"),
+        (&[code(KeyCode::Backspace)], "command-line-backspace"),
+    ] {
+        command_line.bind(keys, command);
+    }
+    command_line
+}
+
+fn palette_bindings(mut palette: Keymap) -> Keymap {
+    for (keys, command) in [
+        (&[code(KeyCode::Enter)][..], "palette-execute"),
+        (&[code(KeyCode::Esc)], "palette-cancel"),
+        (&[code(KeyCode::Backspace)], "palette-backspace"),
+        (&[code(KeyCode::Down)], "palette-next"),
+        (&[ctrl('n')], "palette-next"),
+        (&[code(KeyCode::Up)], "palette-previous"),
+        (&[ctrl('p')], "palette-previous"),
+    ] {
+        palette.bind(keys, command);
+    }
+    palette
+}
+
+fn search_bindings(mut search: Keymap) -> Keymap {
+    for (keys, command) in [
+        (&[code(KeyCode::Enter)][..], "search-accept"),
+        (&[code(KeyCode::Esc)], "search-cancel"),
+        (&[code(KeyCode::Backspace)], "search-backspace"),
+        (&[code(KeyCode::Down)], "search-next"),
+        (&[ctrl('n')], "search-next"),
+        (&[code(KeyCode::Up)], "search-previous"),
+        (&[ctrl('p')], "search-previous"),
+        (&[alt('c')], "search-toggle-case"),
+    ] {
+        search.bind(keys, command);
+    }
+    search
+}
+
+// Only the answers, nothing else may edit the text while a substitution waits for one
+fn confirm_bindings() -> Keymap {
+    let mut confirm = Keymap::default();
+    for (keys, command) in [
+        (&[key('y')][..], "confirm-replace"),
+        (&[key('n')], "confirm-skip"),
+        (&[key('a')], "confirm-replace-all"),
+        (&[key('q')], "confirm-quit"),
+        (&[code(KeyCode::Esc)], "confirm-quit"),
+    ] {
+        confirm.bind(keys, command);
+    }
+    confirm
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chords_parse_into_key_sequences() {
+        assert_eq!(parse_chord("g g"), Ok(vec![key('g'), key('g')]));
+        assert_eq!(parse_chord("Ctrl+K Ctrl+C"), Ok(vec![ctrl('k'), ctrl('c')]));
+        assert_eq!(parse_chord("Alt+Shift+v"), Ok(vec![alt('V')]));
+        assert_eq!(parse_chord("shift+tab  F5"), Ok(vec![code(KeyCode::BackTab), code(KeyCode::F(5))]));
+        assert_eq!(parse_chord("Ctrl++ +"), Ok(vec![ctrl('+'), key('+')]));
+        assert_eq!(parse_chord("Hyper+x"), Err("unknown modifier `Hyper`".to_string()));
+        assert_eq!(parse_chord("Ctrl+Escp"), Err("unknown key `Escp`".to_string()));
+        assert_eq!(parse_chord(" "), Err("no keys given".to_string()));
+        for chord in ["Ctrl+K Ctrl+C", "Ctrl+Shift+P", "g g", "Alt+V", "Shift+Tab", "Space", "PageDown F12", "Esc"] {
+            assert_eq!(parse_chord(chord).map(|keys| chord_to_string(&keys)), Ok(chord.to_string()));
+        }
+    }
+}
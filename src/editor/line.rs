@@ -0,0 +1,211 @@
+use std::borrow::Cow;
+use std::fmt;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const TAB_WIDTH: usize = 4;
+
+// One grapheme cluster of a line. `col` counts chars, `byte` counts bytes and `x` counts
+// screen cells, the three are never interchangeable once a line leaves ASCII
+#[derive(Copy, Clone, Debug)]
+struct Fragment {
+    byte: usize,
+    len_bytes: usize,
+    col: usize,
+    len_chars: usize,
+    x: usize,
+    width: usize,
+}
+
+// A line of text laid out by grapheme cluster and display width, borrowing the text when it can
+pub struct Line<'a> {
+    text: Cow<'a, str>,
+    fragments: Vec<Fragment>,
+}
+
+impl<'a> Line<'a> {
+    pub fn new(text: impl Into<Cow<'a, str>>) -> Self {
+        let text = text.into();
+        let mut fragments = Vec::new();
+        let (mut col, mut x) = (0, 0);
+        for (byte, grapheme) in text.grapheme_indices(true) {
+            let width = grapheme_width(grapheme, x);
+            let len_chars = grapheme.chars().count();
+            fragments.push(Fragment { byte, len_bytes: grapheme.len(), col, len_chars, x, width });
+            col += len_chars;
+            x += width;
+        }
+        Self { text, fragments }
+    }
+
+    // Length in chars
+    pub fn len(&self) -> usize {
+        self.fragments.last().map_or(0, |fragment| fragment.col + fragment.len_chars)
+    }
+
+    // Width in screen cells
+    pub fn width(&self) -> usize {
+        self.fragments.last().map_or(0, |fragment| fragment.x + fragment.width)
+    }
+
+    // Start column and text of each grapheme
+    pub fn graphemes(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.fragments.iter().map(|fragment| (fragment.col, &self.text[fragment.byte..fragment.byte + fragment.len_bytes]))
+    }
+
+    // Index of the fragment containing the char at `col`
+    fn fragment_at(&self, col: usize) -> Option<usize> {
+        match self.fragments.binary_search_by(|fragment| fragment.col.cmp(&col)) {
+            Ok(idx) => Some(idx),
+            Err(0) => None,
+            Err(idx) if col < self.len() => Some(idx - 1),
+            Err(_) => None,
+        }
+    }
+
+    // Start of the grapheme after the one at `col`
+    pub fn next_boundary(&self, col: usize) -> usize {
+        self.fragment_at(col)
+            .map_or_else(|| self.len(), |idx| self.fragments[idx].col + self.fragments[idx].len_chars)
+    }
+
+    // Start of the grapheme before `col`
+    pub fn prev_boundary(&self, col: usize) -> usize {
+        if col == 0 {
+            return 0;
+        }
+        self.fragment_at(col - 1).map_or(0, |idx| self.fragments[idx].col)
+    }
+
+    // Screen column where the grapheme at `col` starts
+    pub fn col_to_x(&self, col: usize) -> usize {
+        self.fragment_at(col).map_or_else(|| self.width(), |idx| self.fragments[idx].x)
+    }
+
+    // Column of the grapheme covering screen column `x`, or the end of the line past it
+    pub fn x_to_col(&self, x: usize) -> usize {
+        self.fragments
+            .iter()
+            .find(|fragment| x < fragment.x + fragment.width)
+            .map_or_else(|| self.len(), |fragment| fragment.col)
+    }
+
+    // Text covering the screen columns `start_x..start_x + width`, wide graphemes cut by either
+    // edge are replaced by spaces so the output is exactly as wide as what it covers
+    pub fn visible(&self, start_x: usize, width: usize) -> String {
+        let end_x = start_x + width;
+        let mut result = String::new();
+        for fragment in &self.fragments {
+            let fragment_end = fragment.x + fragment.width;
+            if fragment.x < start_x && fragment_end <= start_x {
+                continue;
+            }
+            if fragment.x >= end_x {
+                break;
+            }
+            let grapheme = &self.text[fragment.byte..fragment.byte + fragment.len_bytes];
+            if fragment.x < start_x || fragment_end > end_x {
+                let covered = fragment_end.min(end_x) - fragment.x.max(start_x);
+                result.push_str(&" ".repeat(covered));
+            } else if grapheme == "\t" {
+                result.push_str(&" ".repeat(fragment.width));
+            } else if grapheme.chars().any(char::is_control) {
+                result.push_str(&"?".repeat(fragment.width));
+            } else {
+                result.push_str(grapheme);
+            }
+        }
+        result
+    }
+
+    // Screen columns where each row starts when the line is wrapped to `first_width` cells,
+    // and `rest_width` for the rows after the first. At words, a row breaks after the last
+    // blank in it when there is one, and blanks at the end of a row may hang past its edge
+    pub fn wrap(&self, first_width: usize, rest_width: usize, at_words: bool) -> Vec<usize> {
+        let mut starts = vec![0];
+        let (mut row_start, mut width) = (0, first_width.max(1));
+        let mut after_blank: Option<usize> = None; // where the row could break at a word
+        for fragment in &self.fragments {
+            let end = fragment.x + fragment.width;
+            let blank = self.text[fragment.byte..fragment.byte + fragment.len_bytes].chars().all(char::is_whitespace);
+            while end > row_start + width && fragment.x > row_start && !(at_words && blank) {
+                let start = match after_blank.take() {
+                    Some(x) if at_words && x > row_start => x,
+                    _ => fragment.x,
+                };
+                starts.push(start);
+                row_start = start;
+                width = rest_width.max(1);
+            }
+            if blank {
+                after_blank = Some(end);
+            }
+        }
+        starts
+    }
+}
+
+impl fmt::Display for Line<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(&self.text)
+    }
+}
+
+// Cells a grapheme takes when it starts at screen column `x`
+fn grapheme_width(grapheme: &str, x: usize) -> usize {
+    if grapheme == "\t" {
+        TAB_WIDTH - x % TAB_WIDTH
+    } else if grapheme.chars().any(char::is_control) {
+        1
+    } else {
+        grapheme.width()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boundaries_follow_grapheme_clusters() {
+        // "e" + combining acute, then a family emoji joined by zero-width joiners
+        let line = Line::new("ae\u{301}👨\u{200d}👩\u{200d}👧b".to_string());
+        assert_eq!(line.next_boundary(0), 1);
+        assert_eq!(line.next_boundary(1), 3);
+        assert_eq!(line.next_boundary(3), 8);
+        assert_eq!(line.prev_boundary(8), 3);
+        assert_eq!(line.prev_boundary(3), 1);
+        assert_eq!(line.len(), 9);
+    }
+
+    #[test]
+    fn columns_and_screen_cells_stay_distinct() {
+        let line = Line::new("é日本x".to_string());
+        assert_eq!(line.col_to_x(1), 1);
+        assert_eq!(line.col_to_x(2), 3);
+        assert_eq!(line.col_to_x(3), 5);
+        assert_eq!(line.x_to_col(2), 1);
+        assert_eq!(line.width(), 6);
+    }
+
+    #[test]
+    fn visible_clips_by_display_width() {
+        let line = Line::new("日本語".to_string());
+        assert_eq!(line.visible(0, 3), "日 ");
+        assert_eq!(line.visible(1, 4), " 本 ");
+        assert_eq!(Line::new("a\tb".to_string()).visible(0, 8), "a   b");
+    }
+
+    #[test]
+    fn wrap_breaks_after_blanks_or_anywhere() {
+        let line = Line::new("one two three fourteen".to_string());
+        assert_eq!(line.wrap(10, 10, true), [0, 8, 14]);
+        assert_eq!(line.wrap(10, 10, false), [0, 10, 20]);
+        // Rows after the first are narrower, leaving room for the wrap indicator
+        assert_eq!(line.wrap(10, 8, true), [0, 8, 14]);
+        assert_eq!(Line::new("abcdefghij".to_string()).wrap(4, 3, true), [0, 4, 7]);
+        // A wide grapheme that doesn't fit moves to the next row whole
+        assert_eq!(Line::new("ab日本".to_string()).wrap(3, 3, false), [0, 2, 4]);
+        assert_eq!(Line::new(String::new()).wrap(3, 3, true), [0]);
+    }
+}
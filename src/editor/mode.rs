@@ -0,0 +1,54 @@
+use crossterm::cursor::SetCursorStyle;
+
+use super::selection::SelectionKind;
+
+// What keys do. Select works on a selection like Visual but is entered from Insert, typing
+// replaces the selection and leaving it goes back to Insert
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    Normal,
+    #[default]
+    Insert,
+    Visual(SelectionKind),
+    Select(SelectionKind),
+    Command,
+    Palette,
+    Search,
+    Confirm, // asking y/n/a/q about each match of a `:s///c`
+}
+
+impl Mode {
+    pub fn selection_kind(self) -> Option<SelectionKind> {
+        match self {
+            Self::Visual(kind) | Self::Select(kind) => Some(kind),
+            _ => None,
+        }
+    }
+
+    // As shown in the status bar
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Normal => "NORMAL",
+            Self::Insert => "INSERT",
+            Self::Visual(SelectionKind::Char) => "VISUAL",
+            Self::Visual(SelectionKind::Line) => "V-LINE",
+            Self::Visual(SelectionKind::Block) => "V-BLOCK",
+            Self::Select(SelectionKind::Char) => "SELECT",
+            Self::Select(SelectionKind::Line) => "S-LINE",
+            Self::Select(SelectionKind::Block) => "S-BLOCK",
+            Self::Command => "COMMAND",
+            Self::Palette => "PALETTE",
+            Self::Search => "SEARCH",
+            Self::Confirm => "CONFIRM",
+        }
+    }
+
+    pub fn cursor_style(self) -> SetCursorStyle {
+        match self {
+            Self::Normal | Self::Confirm => SetCursorStyle::SteadyBlock,
+            Self::Insert => SetCursorStyle::BlinkingBlock,
+            Self::Visual(_) | Self::Select(_) => SetCursorStyle::BlinkingUnderScore,
+            Self::Command | Self::Palette | Self::Search => SetCursorStyle::BlinkingBar,
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use super::buffer::Location;
+use super::document::Document;
+
+// Cursor movements that operators can act on
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordForward,
+    WordBackward,
+    WordEnd,
+    LineStart,
+    LineEnd,
+    FirstLine,
+    LastLine,
+}
+
+// What an operator does with the text a motion moved over
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+impl Motion {
+    // Where the motion lands from `from`, `count` repeats it or picks a line for gg and G
+    pub fn target(self, document: &Document, from: Location, count: Option<usize>) -> Location {
+        let times = count.unwrap_or(1);
+        match self {
+            Self::Left => {
+                let line = document.line(from.line);
+                let col = (0..times).fold(from.col, |col, _| line.prev_boundary(col));
+                Location { col, ..from }
+            }
+            Self::Right => {
+                let line = document.line(from.line);
+                let col = (0..times).fold(from.col, |col, _| line.next_boundary(col));
+                Location { col, ..from }
+            }
+            Self::Up => keep_x(document, from, from.line.saturating_sub(times)),
+            Self::Down => keep_x(document, from, (from.line + times).min(document.last_line())),
+            Self::WordForward => (0..times).fold(from, |at, _| word_forward(document, at)),
+            Self::WordBackward => (0..times).fold(from, |at, _| word_backward(document, at)),
+            Self::WordEnd => (0..times).fold(from, |at, _| word_end(document, at)),
+            Self::LineStart => Location { line: from.line, col: 0 },
+            Self::LineEnd => {
+                let line = (from.line + times - 1).min(document.last_line());
+                Location { line, col: document.line_len(line) }
+            }
+            Self::FirstLine => document.first_non_blank(count.map_or(0, |line| line - 1).min(document.last_line())),
+            Self::LastLine => {
+                document.first_non_blank(count.map_or(document.last_line(), |line| line - 1).min(document.last_line()))
+            }
+        }
+    }
+
+    // Operators take whole lines for motions that move between lines
+    pub fn linewise(self) -> bool {
+        matches!(self, Self::Up | Self::Down | Self::FirstLine | Self::LastLine)
+    }
+
+    // Operators include the grapheme the motion lands on
+    pub fn inclusive(self) -> bool {
+        self == Self::WordEnd
+    }
+}
+
+// `[start, end)` of the run of word, punctuation or blank graphemes at `at`, or the line's last
+// run past its end. What a double click selects
+pub fn word_around(document: &Document, at: Location) -> (Location, Location) {
+    let graphemes: Vec<(usize, Class)> =
+        document.line(at.line).graphemes().map(|(col, grapheme)| (col, classify(grapheme))).collect();
+    let Some(idx) = graphemes.iter().rposition(|(col, _)| *col <= at.col) else {
+        return (at, at);
+    };
+    let class = graphemes[idx].1;
+    let first = graphemes[..idx].iter().rposition(|(_, other)| *other != class).map_or(0, |before| before + 1);
+    let last = graphemes[idx..].iter().position(|(_, other)| *other != class).map_or(graphemes.len(), |n| idx + n);
+    let end = graphemes.get(last).map_or_else(|| document.line_len(at.line), |(col, _)| *col);
+    (Location { col: graphemes[first].0, ..at }, Location { col: end, ..at })
+}
+
+// Same screen column on another line
+fn keep_x(document: &Document, from: Location, line: usize) -> Location {
+    let x = document.line(from.line).col_to_x(from.col);
+    Location { line, col: document.line(line).x_to_col(x) }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Class {
+    Blank,
+    Word,
+    Punctuation,
+    LineEnd,
+}
+
+fn classify(grapheme: &str) -> Class {
+    match grapheme.chars().next() {
+        Some(c) if c.is_whitespace() => Class::Blank,
+        Some(c) if c.is_alphanumeric() || c == '_' => Class::Word,
+        _ => Class::Punctuation,
+    }
+}
+
+// Walks the document grapheme by grapheme, with a stop at the end of every line
+struct Scanner<'a> {
+    document: &'a Document,
+    line: usize,
+    graphemes: Vec<(usize, Class)>,
+    idx: usize, // graphemes.len() is the end of the line
+}
+
+impl<'a> Scanner<'a> {
+    fn new(document: &'a Document, at: Location) -> Self {
+        let mut scanner = Self { document, line: at.line, graphemes: Vec::new(), idx: 0 };
+        scanner.load(at.line);
+        scanner.idx = scanner.graphemes.iter().position(|(col, _)| *col >= at.col).unwrap_or(scanner.graphemes.len());
+        scanner
+    }
+
+    fn load(&mut self, line: usize) {
+        self.line = line;
+        let line = self.document.line(line);
+        self.graphemes = line.graphemes().map(|(col, grapheme)| (col, classify(grapheme))).collect();
+    }
+
+    fn class(&self) -> Class {
+        self.graphemes.get(self.idx).map_or(Class::LineEnd, |(_, class)| *class)
+    }
+
+    fn location(&self) -> Location {
+        let col = self.graphemes.get(self.idx).map_or_else(|| self.document.line_len(self.line), |(col, _)| *col);
+        Location { line: self.line, col }
+    }
+
+    fn is_empty_line(&self) -> bool {
+        self.graphemes.is_empty()
+    }
+
+    fn forward(&mut self) -> bool {
+        if self.idx < self.graphemes.len() {
+            self.idx += 1;
+        } else if self.line < self.document.last_line() {
+            self.load(self.line + 1);
+            self.idx = 0;
+        } else {
+            return false;
+        }
+        true
+    }
+
+    fn backward(&mut self) -> bool {
+        if self.idx > 0 {
+            self.idx -= 1;
+        } else if self.line > 0 {
+            self.load(self.line - 1);
+            self.idx = self.graphemes.len();
+        } else {
+            return false;
+        }
+        true
+    }
+
+    // Past blanks and line ends, `stop_at` is where the motion started when an empty line after it
+    // counts as a word
+    fn skip_blanks(&mut self, stop_at: Option<Location>, step: fn(&mut Self) -> bool) {
+        while matches!(self.class(), Class::Blank | Class::LineEnd) {
+            if self.is_empty_line() && stop_at.is_some_and(|origin| self.location() != origin) {
+                return;
+            }
+            if !step(self) {
+                return;
+            }
+        }
+    }
+}
+
+// w: start of the next word
+fn word_forward(document: &Document, from: Location) -> Location {
+    let mut scanner = Scanner::new(document, from);
+    let class = scanner.class();
+    if matches!(class, Class::Word | Class::Punctuation) {
+        while scanner.class() == class {
+            if !scanner.forward() {
+                return scanner.location();
+            }
+        }
+    }
+    scanner.skip_blanks(Some(from), Scanner::forward);
+    scanner.location()
+}
+
+// e: last grapheme of the current or next word
+fn word_end(document: &Document, from: Location) -> Location {
+    let mut scanner = Scanner::new(document, from);
+    if !scanner.forward() {
+        return from;
+    }
+    scanner.skip_blanks(None, Scanner::forward);
+    let class = scanner.class();
+    while scanner.forward() {
+        if scanner.class() != class {
+            scanner.backward();
+            break;
+        }
+    }
+    scanner.location()
+}
+
+// b: start of the current or previous word
+fn word_backward(document: &Document, from: Location) -> Location {
+    let mut scanner = Scanner::new(document, from);
+    if !scanner.backward() {
+        return from;
+    }
+    scanner.skip_blanks(Some(from), Scanner::backward);
+    let class = scanner.class();
+    if class == Class::LineEnd {
+        return scanner.location();
+    }
+    while scanner.backward() {
+        if scanner.class() != class {
+            scanner.forward();
+            break;
+        }
+    }
+    scanner.location()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_motions_stop_at_words_and_punctuation() {
+        let document = Document::from_text("foo.bar  baz\n\nqux");
+        let w = |from| Motion::WordForward.target(&document, from, None);
+        assert_eq!(w(Location::new(0, 0)), Location::new(0, 3));
+        assert_eq!(w(Location::new(0, 3)), Location::new(0, 4));
+        assert_eq!(w(Location::new(0, 4)), Location::new(0, 9));
+        assert_eq!(w(Location::new(0, 9)), Location::new(1, 0));
+        assert_eq!(w(Location::new(1, 0)), Location::new(2, 0));
+        assert_eq!(Motion::WordEnd.target(&document, Location::new(0, 4), Some(2)), Location::new(0, 11));
+        assert_eq!(Motion::WordBackward.target(&document, Location::new(2, 0), None), Location::new(1, 0));
+        assert_eq!(Motion::WordBackward.target(&document, Location::new(0, 10), None), Location::new(0, 9));
+    }
+
+    #[test]
+    fn words_around_a_location() {
+        let document = Document::from_text("foo.bar  baz\n");
+        assert_eq!(word_around(&document, Location::new(0, 5)), (Location::new(0, 4), Location::new(0, 7)));
+        assert_eq!(word_around(&document, Location::new(0, 3)), (Location::new(0, 3), Location::new(0, 4)));
+        assert_eq!(word_around(&document, Location::new(0, 7)), (Location::new(0, 7), Location::new(0, 9)));
+        assert_eq!(word_around(&document, Location::new(0, 20)), (Location::new(0, 9), Location::new(0, 12)));
+        assert_eq!(word_around(&document, Location::new(1, 0)), (Location::new(1, 0), Location::new(1, 0)));
+    }
+
+    #[test]
+    fn line_motions_take_counts() {
+        let document = Document::from_text("one\n  two\nthree");
+        assert_eq!(Motion::LastLine.target(&document, Location::new(0, 2), None), Location::new(2, 0));
+        assert_eq!(Motion::FirstLine.target(&document, Location::new(0, 2), Some(2)), Location::new(1, 2));
+        assert_eq!(Motion::LineEnd.target(&document, Location::new(0, 0), Some(2)), Location::new(1, 5));
+        assert_eq!(Motion::Down.target(&document, Location::new(0, 3), Some(5)), Location::new(2, 3));
+    }
+}
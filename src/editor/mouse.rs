@@ -0,0 +1,39 @@
+use std::time::{Duration, Instant};
+
+use super::terminal::Position;
+
+const MULTI_CLICK_TIME: Duration = Duration::from_millis(500);
+pub const WHEEL_ROWS: usize = 3; // screen rows scrolled per notch of the wheel
+
+// Counts quick clicks on the same spot, 1 to 3 for a single, double and triple click. A fourth
+// starts over at a single click
+#[derive(Default)]
+pub struct Clicks {
+    last: Option<(Position, Instant, usize)>,
+}
+
+impl Clicks {
+    pub fn click(&mut self, position: Position, now: Instant) -> usize {
+        let count = match self.last {
+            Some((at, time, count)) if at == position && now.duration_since(time) < MULTI_CLICK_TIME => count % 3 + 1,
+            _ => 1,
+        };
+        self.last = Some((position, now, count));
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quick_clicks_on_one_spot_count_up() {
+        let mut clicks = Clicks::default();
+        let (here, now) = (Position { x: 3, y: 1 }, Instant::now());
+        let counts: Vec<usize> = (0..4).map(|n| clicks.click(here, now + Duration::from_millis(n * 100))).collect();
+        assert_eq!(counts, [1, 2, 3, 1]);
+        assert_eq!(clicks.click(Position { x: 4, y: 1 }, now + Duration::from_millis(450)), 1);
+        assert_eq!(clicks.click(Position { x: 4, y: 1 }, now + Duration::from_secs(2)), 1);
+    }
+}
@@ -0,0 +1,136 @@
+use crossterm::style::Color;
+
+use super::keymap::{chord_to_string, Keymaps};
+use super::line::Line;
+use super::mode::Mode;
+use super::registry::{Command, Registry};
+use super::terminal::{Frame, Position};
+use super::view::to_u16;
+
+const MAX_ENTRIES: usize = 10;
+const RECENT_LIMIT: usize = 10;
+const SELECTED_BACKGROUND: Color = Color::DarkGrey;
+const BINDING_FOREGROUND: Color = Color::DarkCyan;
+
+// Fuzzy finder over the registry, drawn on top of the document
+#[derive(Default)]
+pub struct Palette {
+    pub query: String,
+    pub selected: usize,
+    pub return_mode: Mode, // where the palette was opened from, its bindings are the ones shown
+    recent: Vec<&'static str>, // names of the commands run from the palette, latest first
+}
+
+impl Palette {
+    pub fn open(&mut self, return_mode: Mode) {
+        self.query.clear();
+        self.selected = 0;
+        self.return_mode = return_mode;
+    }
+
+    pub fn record(&mut self, name: &'static str) {
+        self.recent.retain(|recent| *recent != name);
+        self.recent.insert(0, name);
+        self.recent.truncate(RECENT_LIMIT);
+    }
+
+    // Commands matching the query, best first. Recently run commands get a boost that fades
+    // with age, so with an empty query they come first
+    pub fn matches(&self, registry: &Registry) -> Vec<Command> {
+        let mut scored: Vec<(usize, Command)> = registry
+            .iter()
+            .filter(|command| !command.is_mode_internal())
+            .filter_map(|command| {
+                let name = fuzzy_score(&self.query, command.name).map(|score| score * 2);
+                let score = name.max(fuzzy_score(&self.query, command.description))?;
+                let recency = self.recent.iter().position(|recent| *recent == command.name).map_or(0, |age| RECENT_LIMIT - age);
+                Some((score + recency, *command))
+            })
+            .collect();
+        // Stable, so equal scores keep the registry's order
+        scored.sort_by(|(a, _), (b, _)| b.cmp(a));
+        scored.into_iter().map(|(_, command)| command).collect()
+    }
+
+    // The prompt on the top row and the matches below it, the cursor is left after the query
+    pub fn draw(&self, registry: &Registry, keymaps: &Keymaps, frame: &mut Frame, width: usize, height: usize) {
+        let matches = self.matches(registry);
+        let rows = matches.len().min(MAX_ENTRIES).min(height.saturating_sub(1));
+        // Scroll the list so the selected entry stays in it
+        let first = self.selected.saturating_sub(rows.saturating_sub(1));
+        for (row, command) in matches.iter().skip(first).take(rows).enumerate() {
+            frame.move_cursor_to(Position { x: 0, y: to_u16(row + 1) });
+            frame.clear_line();
+            let selected = first + row == self.selected;
+            if selected {
+                frame.set_background_color(SELECTED_BACKGROUND);
+            }
+            let binding = keymaps.binding(self.return_mode, command.name).map(chord_to_string).unwrap_or_default();
+            let binding_width = Line::new(binding.clone()).width();
+            let text = Line::new(format!(" {}  {}", command.name, command.description));
+            let text_width = width.saturating_sub(binding_width + 1);
+            let shown = text.visible(0, text_width);
+            let padding = width.saturating_sub(Line::new(shown.clone()).width() + binding_width);
+            frame.print(&format!("{shown}{}", " ".repeat(padding)));
+            frame.set_foreground_color(BINDING_FOREGROUND);
+            frame.print(&Line::new(binding).visible(0, width.saturating_sub(1)));
+            frame.set_foreground_color(Color::Reset);
+            if selected {
+                frame.set_background_color(Color::Reset);
+            }
+        }
+        let prompt = Line::new(format!("> {}", self.query));
+        frame.move_cursor_to(Position { x: 0, y: 0 });
+        frame.clear_line();
+        frame.print(&prompt.visible(0, width));
+        frame.move_cursor_to(Position { x: to_u16(prompt.width().min(width.saturating_sub(1))), y: 0 });
+    }
+}
+
+// Score for the query's chars appearing in order in `text`, ignoring case, None if they don't.
+// Runs of consecutive chars and chars starting a word count for more
+fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+    let mut score = 0;
+    let mut chars = text.char_indices();
+    let mut previous: Option<usize> = None; // index in `text` of the last matched char
+    for wanted in query.chars().filter(|c| !c.is_whitespace()) {
+        let (idx, _) = chars.find(|(_, c)| c.to_lowercase().eq(wanted.to_lowercase()))?;
+        let word_start = text[..idx].chars().next_back().is_none_or(|before| !before.is_alphanumeric());
+        score += 1;
+        if word_start {
+            score += 3;
+        }
+        if previous.is_some_and(|previous| text[previous..idx].chars().count() == 1) {
+            score += 5;
+        }
+        previous = Some(idx);
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_matches_prefer_word_starts_and_runs() {
+        assert_eq!(fuzzy_score("", "quit"), Some(0));
+        assert_eq!(fuzzy_score("xyz", "quit"), None);
+        assert_eq!(fuzzy_score("Dc", "delete-char"), Some(8));
+        assert!(fuzzy_score("del", "delete-char") > fuzzy_score("del", "model-line"));
+        assert!(fuzzy_score("dc", "delete-char") > fuzzy_score("dc", "dedent-selection"));
+    }
+
+    #[test]
+    fn recently_run_commands_rank_first() {
+        let registry = Registry::default();
+        let mut palette = Palette::default();
+        assert_eq!(palette.matches(&registry)[0].name, "quit");
+        palette.record("redo");
+        palette.record("save");
+        let names: Vec<_> = palette.matches(&registry).iter().take(3).map(|command| command.name).collect();
+        assert_eq!(names, ["save", "redo", "quit"]);
+        palette.query = "sav".to_string();
+        assert_eq!(palette.matches(&registry)[0].name, "save");
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+// Vim style registers: "a-"z named, "0 holds the last yank, "- the last delete and the unnamed
+// register "" whatever was yanked or deleted last. Writing to "A-"Z appends to "a-"z. "+ is the
+// system clipboard, the editor handles it before it gets here.
+#[derive(Default)]
+pub struct Registers {
+    named: HashMap<char, String>,
+    yank: Option<String>,
+    delete: Option<String>,
+    unnamed: Option<String>,
+}
+
+impl Registers {
+    pub fn is_valid_name(name: char) -> bool {
+        name.is_ascii_alphabetic() || matches!(name, '"' | '0' | '-' | '+')
+    }
+
+    // Store yanked text in the given register, or "0 when none was picked
+    pub fn yank(&mut self, name: Option<char>, text: String) {
+        if !self.write_named(name, &text) {
+            self.yank = Some(text.clone());
+        }
+        self.unnamed = Some(text);
+    }
+
+    // Store deleted text in the given register, or "- when none was picked
+    pub fn delete(&mut self, name: Option<char>, text: String) {
+        if !self.write_named(name, &text) {
+            self.delete = Some(text.clone());
+        }
+        self.unnamed = Some(text);
+    }
+
+    // Contents of a register, None reads the unnamed register
+    pub fn get(&self, name: Option<char>) -> Option<&str> {
+        match name.unwrap_or('"') {
+            '"' => self.unnamed.as_deref(),
+            '0' => self.yank.as_deref(),
+            '-' => self.delete.as_deref(),
+            name => self.named.get(&name.to_ascii_lowercase()).map(String::as_str),
+        }
+    }
+
+    // Returns false if the name doesn't pick a named register
+    fn write_named(&mut self, name: Option<char>, text: &str) -> bool {
+        match name {
+            Some(name) if name.is_ascii_lowercase() => {
+                self.named.insert(name, text.to_string());
+                true
+            }
+            Some(name) if name.is_ascii_uppercase() => {
+                self.named.entry(name.to_ascii_lowercase()).or_default().push_str(text);
+                true
+            }
+            Some('0') => {
+                self.yank = Some(text.to_string());
+                true
+            }
+            Some('-') => {
+                self.delete = Some(text.to_string());
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yank_fills_yank_and_unnamed_registers() {
+        let mut registers = Registers::default();
+        registers.yank(None, "one".to_string());
+        registers.delete(None, "two".to_string());
+        assert_eq!(registers.get(Some('0')), Some("one"));
+        assert_eq!(registers.get(Some('-')), Some("two"));
+        assert_eq!(registers.get(None), Some("two"));
+    }
+
+    #[test]
+    fn uppercase_names_append() {
+        let mut registers = Registers::default();
+        registers.yank(Some('a'), "foo".to_string());
+        registers.yank(Some('A'), "bar".to_string());
+        assert_eq!(registers.get(Some('a')), Some("foobar"));
+        assert_eq!(registers.get(Some('0')), None);
+        assert_eq!(registers.get(None), Some("bar"));
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::io::Error;
+
+use super::commands;
+use super::motion::{Motion, Operator};
+use super::Editor;
+
+// Handlers get the count typed before the key, if any
+pub type Handler = fn(&mut Editor, Option<usize>) -> Result<(), Error>;
+
+#[derive(Copy, Clone)]
+pub enum Action {
+    Run(Handler),
+    Motion(Motion),
+    Operator(Operator),
+}
+
+// Something the editor can do, found by name from key bindings, the config and the `:` line
+#[derive(Copy, Clone)]
+pub struct Command {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub action: Action,
+}
+
+// Commands that only mean something inside their own mode's prompt, the palette leaves them out.
+// Run from there they would act on the palette itself, palette-execute forever
+const MODE_INTERNAL_PREFIXES: &[&str] = &["palette-", "confirm-", "search-accept", "search-cancel", "search-backspace"];
+const MODE_INTERNAL: &[&str] = &["execute-command-line", "cancel-command-line", "command-line-backspace"];
+
+impl Command {
+    pub fn is_mode_internal(&self) -> bool {
+        MODE_INTERNAL.contains(&self.name) || MODE_INTERNAL_PREFIXES.iter().any(|prefix| self.name.starts_with(prefix))
+    }
+}
+
+// Every command by name, in the order they were registered
+pub struct Registry {
+    commands: Vec<Command>,
+    by_name: HashMap<&'static str, usize>,
+}
+
+impl Registry {
+    // A later command under the same name replaces the earlier one
+    pub fn register(&mut self, command: Command) {
+        if let Some(&idx) = self.by_name.get(command.name) {
+            self.commands[idx] = command;
+        } else {
+            self.by_name.insert(command.name, self.commands.len());
+            self.commands.push(command);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.by_name.get(name).map(|&idx| &self.commands[idx])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Command> {
+        self.commands.iter()
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Self { commands: Vec::new(), by_name: HashMap::new() };
+        for &(name, description, action) in BUILTIN {
+            registry.register(Command { name, description, action });
+        }
+        registry
+    }
+}
+
+const BUILTIN: &[(&str, &str, Action)] = &[
+    ("quit", "Quit the editor", Action::Run(commands::quit)),
+    ("save", "Write the file to disk", Action::Run(commands::save)),
+    ("undo", "Undo the last change", Action::Run(commands::undo)),
+    ("redo", "Redo the last undone change", Action::Run(commands::redo)),
+    ("move-up", "Move the cursor up a line", Action::Run(commands::move_up)),
+    ("move-down", "Move the cursor down a line, adding one at the end", Action::Run(commands::move_down)),
+    ("move-left", "Move the cursor left", Action::Run(commands::move_left)),
+    ("move-right", "Move the cursor right", Action::Run(commands::move_right)),
+    ("insert-newline", "Split the line at the cursor", Action::Run(commands::insert_newline)),
+    ("delete-backward", "Delete the character before the cursor", Action::Run(commands::delete_backward)),
+    ("paste", "Paste before the cursor", Action::Run(commands::paste)),
+    ("paste-after", "Paste after the cursor, or below the line", Action::Run(commands::paste_after)),
+    ("paste-over-selection", "Replace the selection with the register", Action::Run(commands::paste_over_selection)),
+    ("pick-register", "Use the register named by the next key", Action::Run(commands::pick_register)),
+    ("normal-mode", "Switch to normal mode", Action::Run(commands::normal_mode)),
+    ("insert-mode", "Switch to insert mode", Action::Run(commands::insert)),
+    ("append", "Insert after the cursor", Action::Run(commands::append)),
+    ("insert-at-line-start", "Insert before the first non-blank", Action::Run(commands::insert_at_line_start)),
+    ("append-at-line-end", "Insert at the end of the line", Action::Run(commands::append_at_line_end)),
+    ("open-line-below", "Start a new line below", Action::Run(commands::open_line_below)),
+    ("open-line-above", "Start a new line above", Action::Run(commands::open_line_above)),
+    ("visual-mode", "Select characters", Action::Run(commands::visual_char)),
+    ("visual-line-mode", "Select whole lines", Action::Run(commands::visual_line)),
+    ("visual-block-mode", "Select a rectangle", Action::Run(commands::visual_block)),
+    ("select-mode", "Select characters, typing replaces them", Action::Run(commands::select_char)),
+    ("select-line-mode", "Select whole lines, typing replaces them", Action::Run(commands::select_line)),
+    ("select-block-mode", "Select a rectangle, typing fills it", Action::Run(commands::select_block)),
+    ("leave-selection", "Stop selecting", Action::Run(commands::leave_selection)),
+    ("delete-char", "Delete the character under the cursor", Action::Run(commands::delete_char)),
+    ("delete-to-line-end", "Delete to the end of the line", Action::Run(commands::delete_to_line_end)),
+    ("change-to-line-end", "Change to the end of the line", Action::Run(commands::change_to_line_end)),
+    ("copy-selection", "Copy the selection", Action::Run(commands::yank_selection)),
+    ("cut-selection", "Cut the selection", Action::Run(commands::cut_selection)),
+    ("delete-selection", "Delete the selection without copying it", Action::Run(commands::delete_selection)),
+    ("indent-selection", "Indent the selected lines", Action::Run(commands::indent_selection)),
+    ("dedent-selection", "Dedent the selected lines", Action::Run(commands::dedent_selection)),
+    ("uppercase-selection", "Uppercase the selection", Action::Run(commands::uppercase_selection)),
+    ("lowercase-selection", "Lowercase the selection", Action::Run(commands::lowercase_selection)),
+    ("command-mode", "Type a command on the `:` line", Action::Run(commands::command_mode)),
+    ("cancel-command-line", "Leave the `:` line", Action::Run(commands::cancel_command_line)),
+    ("command-line-backspace", "Delete the last character on the `:` line", Action::Run(commands::command_line_backspace)),
+    ("execute-command-line", "Run the command on the `:` line", Action::Run(commands::execute_command_line)),
+    ("command-palette", "Find a command by name and run it", Action::Run(commands::command_palette)),
+    ("palette-execute", "Run the command picked in the palette", Action::Run(commands::palette_execute)),
+    ("palette-cancel", "Close the palette", Action::Run(commands::palette_cancel)),
+    ("palette-backspace", "Delete the last character of the palette query", Action::Run(commands::palette_backspace)),
+    ("palette-next", "Pick the next command in the palette", Action::Run(commands::palette_next)),
+    ("palette-previous", "Pick the previous command in the palette", Action::Run(commands::palette_previous)),
+    ("search", "Search the file as you type", Action::Run(commands::search)),
+    ("search-accept", "Close the search prompt at the match", Action::Run(commands::search_accept)),
+    ("search-cancel", "Close the search prompt where it was opened", Action::Run(commands::search_cancel)),
+    ("search-backspace", "Delete the last character of the search", Action::Run(commands::search_backspace)),
+    ("search-next", "Go to the next match", Action::Run(commands::search_next)),
+    ("search-previous", "Go to the previous match", Action::Run(commands::search_previous)),
+    ("search-toggle-case", "Toggle whether the search matches case", Action::Run(commands::search_toggle_case)),
+    ("clear-search-highlight", "Stop highlighting the matches", Action::Run(commands::clear_search_highlight)),
+    ("confirm-replace", "Replace this match and go to the next", Action::Run(commands::confirm_replace)),
+    ("confirm-skip", "Keep this match and go to the next", Action::Run(commands::confirm_skip)),
+    ("confirm-replace-all", "Replace this match and all the rest", Action::Run(commands::confirm_replace_all)),
+    ("confirm-quit", "Stop replacing", Action::Run(commands::confirm_quit)),
+    ("cycle-line-numbers", "Switch line numbers between off, absolute, relative and hybrid", Action::Run(commands::cycle_line_numbers)),
+    ("toggle-soft-wrap", "Wrap long lines onto the rows below, or scroll them sideways", Action::Run(commands::toggle_soft_wrap)),
+    ("char-left", "Motion: one character left", Action::Motion(Motion::Left)),
+    ("char-right", "Motion: one character right", Action::Motion(Motion::Right)),
+    ("line-up", "Motion: one line up", Action::Motion(Motion::Up)),
+    ("line-down", "Motion: one line down", Action::Motion(Motion::Down)),
+    ("word-forward", "Motion: start of the next word", Action::Motion(Motion::WordForward)),
+    ("word-backward", "Motion: start of the previous word", Action::Motion(Motion::WordBackward)),
+    ("word-end", "Motion: end of the word", Action::Motion(Motion::WordEnd)),
+    ("line-start", "Motion: start of the line", Action::Motion(Motion::LineStart)),
+    ("line-end", "Motion: end of the line", Action::Motion(Motion::LineEnd)),
+    ("first-line", "Motion: first line, or the line given by the count", Action::Motion(Motion::FirstLine)),
+    ("last-line", "Motion: last line, or the line given by the count", Action::Motion(Motion::LastLine)),
+    ("delete", "Operator: delete what the motion covers", Action::Operator(Operator::Delete)),
+    ("change", "Operator: replace what the motion covers", Action::Operator(Operator::Change)),
+    ("yank", "Operator: copy what the motion covers", Action::Operator(Operator::Yank)),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_are_found_by_name_and_can_be_replaced() {
+        let mut registry = Registry::default();
+        assert!(matches!(registry.get("word-end").map(|command| command.action), Some(Action::Motion(Motion::WordEnd))));
+        assert!(registry.get("frobnicate").is_none());
+        let count = registry.iter().count();
+        registry.register(Command { name: "word-end", description: "", action: Action::Operator(Operator::Yank) });
+        assert_eq!(registry.iter().count(), count);
+        assert!(matches!(registry.get("word-end").map(|command| command.action), Some(Action::Operator(Operator::Yank))));
+    }
+}
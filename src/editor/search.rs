@@ -0,0 +1,145 @@
+use regex::{Regex, RegexBuilder};
+
+use super::buffer::Location;
+use super::document::Document;
+use super::mode::Mode;
+use super::view::ScrollOffset;
+
+// State of the `/` prompt and the last search, which n and N repeat
+#[derive(Default)]
+pub struct Search {
+    pub query: String,
+    pub case_sensitive: bool,
+    pub highlight: bool, // matches stay highlighted after the prompt closes, until cleared
+    pub origin: Location, // cursor when the prompt opened, typing searches from here
+    pub origin_scroll: ScrollOffset,
+    pub return_mode: Mode,
+    previous_query: String, // Enter on an empty prompt searches for this again
+}
+
+impl Search {
+    pub fn open(&mut self, cursor: Location, scroll: ScrollOffset, return_mode: Mode) {
+        if !self.query.is_empty() {
+            self.previous_query = std::mem::take(&mut self.query);
+        }
+        self.origin = cursor;
+        self.origin_scroll = scroll;
+        self.return_mode = return_mode;
+        self.highlight = true;
+    }
+
+    // An empty prompt repeats the previous search
+    pub fn accept(&mut self) {
+        if self.query.is_empty() {
+            self.query = std::mem::take(&mut self.previous_query);
+        }
+    }
+
+    // The query as a regex matching it literally, None while there is nothing to look for
+    fn regex(&self) -> Option<Regex> {
+        if self.query.is_empty() {
+            return None;
+        }
+        RegexBuilder::new(&regex::escape(&self.query)).case_insensitive(!self.case_sensitive).build().ok()
+    }
+
+    // Every match on lines `first..=last`, for highlighting the visible rows
+    pub fn matches(&self, document: &Document, first: usize, last: usize) -> Vec<(Location, Location)> {
+        let Some(regex) = self.regex() else {
+            return Vec::new();
+        };
+        let last = last.min(document.last_line());
+        if first > last {
+            return Vec::new();
+        }
+        let text = document.haystack();
+        let (start, end) = (line_start_byte(document, first), line_end_byte(document, last));
+        let location = |byte: usize| document.char_to_location(document.byte_to_char(start + byte));
+        regex.find_iter(&text[start..end]).map(|found| (location(found.start()), location(found.end()))).collect()
+    }
+
+    // Start of the nearest match after `from`, or at it when `inclusive`, going round the end of
+    // the document. The flag says whether the search wrapped
+    pub fn find(&self, document: &Document, from: Location, forward: bool, inclusive: bool) -> Option<(Location, bool)> {
+        let regex = self.regex()?;
+        let text = document.haystack();
+        let at = document.char_to_byte(document.location_to_char(from));
+        let past_at = at + text[at..].chars().next().map_or(0, char::len_utf8);
+        let location = |byte: usize| document.char_to_location(document.byte_to_char(byte));
+        if forward {
+            let start = if inclusive { at } else { past_at };
+            if let Some(found) = regex.find_at(&text, start) {
+                return Some((location(found.start()), false));
+            }
+            regex.find(&text).map(|found| (location(found.start()), true))
+        } else {
+            let limit = if inclusive { past_at } else { at };
+            if let Some(start) = last_match_before(&regex, &text, document, limit) {
+                return Some((location(start), false));
+            }
+            last_match_before(&regex, &text, document, text.len()).map(|start| (location(start), true))
+        }
+    }
+}
+
+// Lines searched at a time going backwards, regexes only run forwards
+const BACKWARD_BLOCK_LINES: usize = 256;
+
+// Start of the last match starting before the byte `limit`. Matches never span lines, so the text
+// is searched a block of lines at a time from the one holding `limit` back to the start
+fn last_match_before(regex: &Regex, text: &str, document: &Document, limit: usize) -> Option<usize> {
+    let mut last = document.char_to_location(document.byte_to_char(limit)).line;
+    loop {
+        let first = last.saturating_sub(BACKWARD_BLOCK_LINES - 1);
+        let start = line_start_byte(document, first);
+        let block = &text[start..line_end_byte(document, last)];
+        let found = regex.find_iter(block).map(|found| start + found.start()).take_while(|&byte| byte < limit).last();
+        if found.is_some() || first == 0 {
+            return found;
+        }
+        last = first - 1;
+    }
+}
+
+fn line_start_byte(document: &Document, line: usize) -> usize {
+    document.char_to_byte(document.location_to_char(Location { line, col: 0 }))
+}
+
+// Byte where the line's text ends, before its line ending
+fn line_end_byte(document: &Document, line: usize) -> usize {
+    document.char_to_byte(document.location_to_char(Location { line, col: usize::MAX }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(query: &str) -> Search {
+        Search { query: query.to_string(), ..Search::default() }
+    }
+
+    #[test]
+    fn find_goes_both_ways_and_wraps() {
+        let document = Document::from_text("foo bar\nBar foo\nbaz");
+        let bar = search("bar");
+        assert_eq!(bar.find(&document, Location::new(0, 4), true, true), Some((Location::new(0, 4), false)));
+        assert_eq!(bar.find(&document, Location::new(0, 4), true, false), Some((Location::new(1, 0), false)));
+        assert_eq!(bar.find(&document, Location::new(1, 0), true, false), Some((Location::new(0, 4), true)));
+        assert_eq!(bar.find(&document, Location::new(0, 4), false, false), Some((Location::new(1, 0), true)));
+        let case_sensitive = Search { case_sensitive: true, ..search("bar") };
+        assert_eq!(case_sensitive.find(&document, Location::new(0, 4), true, false), Some((Location::new(0, 4), true)));
+        assert_eq!(search("qux").find(&document, Location::new(0, 0), true, true), None);
+        assert_eq!(search("ar").matches(&document, 0, 5).len(), 2);
+    }
+
+    #[test]
+    fn matches_count_chars_and_backward_search_crosses_blocks() {
+        let document = Document::from_text(&format!("é foo\n{}é foo", "\n".repeat(BACKWARD_BLOCK_LINES * 2)));
+        let foo = search("FOO");
+        let last = document.last_line();
+        assert_eq!(foo.matches(&document, 0, 0), [(Location::new(0, 2), Location::new(0, 5))]);
+        assert_eq!(foo.find(&document, Location::new(last, 2), false, false), Some((Location::new(0, 2), false)));
+        assert_eq!(foo.find(&document, Location::new(0, 0), false, false), Some((Location::new(last, 2), true)));
+        assert_eq!(foo.find(&document, Location::new(last, 2), false, true), Some((Location::new(last, 2), false)));
+    }
+}
@@ -0,0 +1,90 @@
+use super::buffer::Location;
+use super::document::Document;
+
+// How the text between the anchor and the cursor is selected
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SelectionKind {
+    Char,  // everything from the anchor up to the cursor
+    Line,  // whole lines, line breaks included
+    Block, // the same screen columns on every line
+}
+
+// Text selected in visual mode
+#[derive(Copy, Clone, Debug)]
+pub struct Selection {
+    pub kind: SelectionKind,
+    pub anchor: Location,
+    pub cursor: Location,
+}
+
+impl Selection {
+    // First and last line touched
+    pub fn lines(&self) -> (usize, usize) {
+        let (start, end) = (self.anchor.min(self.cursor), self.anchor.max(self.cursor));
+        (start.line, end.line)
+    }
+
+    // Selected text as `[start, end)` ranges in document order, one per line in block mode
+    pub fn ranges(&self, document: &Document) -> Vec<(Location, Location)> {
+        let (start, end) = (self.anchor.min(self.cursor), self.anchor.max(self.cursor));
+        match self.kind {
+            SelectionKind::Char => vec![(start, end)],
+            SelectionKind::Line => {
+                // Lines at the end of the document take the line break before them instead
+                if end.line < document.last_line() {
+                    vec![(Location { line: start.line, col: 0 }, Location { line: end.line + 1, col: 0 })]
+                } else if start.line > 0 {
+                    let before = Location { line: start.line - 1, col: document.line_len(start.line - 1) };
+                    vec![(before, Location { line: end.line, col: document.line_len(end.line) })]
+                } else {
+                    vec![(Location::default(), Location { line: end.line, col: document.line_len(end.line) })]
+                }
+            }
+            SelectionKind::Block => {
+                let (left, right) = self.columns(document);
+                (start.line..=end.line)
+                    .map(|line_idx| {
+                        let line = document.line(line_idx);
+                        let from = Location { line: line_idx, col: line.x_to_col(left) };
+                        (from, Location { line: line_idx, col: line.x_to_col(right) })
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    // Screen columns `[left, right)` of a block selection
+    pub fn columns(&self, document: &Document) -> (usize, usize) {
+        let anchor_x = document.line(self.anchor.line).col_to_x(self.anchor.col);
+        let cursor_x = document.line(self.cursor.line).col_to_x(self.cursor.col);
+        (anchor_x.min(cursor_x), anchor_x.max(cursor_x))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_selection_covers_whole_lines() {
+        let document = Document::from_text("one\ntwo\nthree");
+        let selection = Selection { kind: SelectionKind::Line, anchor: Location::new(1, 2), cursor: Location::new(0, 1) };
+        assert_eq!(selection.ranges(&document), [(Location::new(0, 0), Location::new(2, 0))]);
+        let selection = Selection { anchor: Location::new(2, 1), ..selection };
+        assert_eq!(selection.ranges(&document), [(Location::new(0, 0), Location::new(2, 5))]);
+        let selection = Selection { cursor: Location::new(1, 0), ..selection };
+        assert_eq!(selection.ranges(&document), [(Location::new(0, 3), Location::new(2, 5))]);
+    }
+
+    #[test]
+    fn block_selection_follows_screen_columns() {
+        let document = Document::from_text("abcd\n日本\nx");
+        let selection = Selection { kind: SelectionKind::Block, anchor: Location::new(0, 1), cursor: Location::new(2, 1) };
+        assert_eq!(selection.columns(&document), (1, 1));
+        let selection = Selection { cursor: Location::new(0, 3), anchor: Location::new(2, 0), ..selection };
+        assert_eq!(
+            selection.ranges(&document),
+            [(Location::new(0, 0), Location::new(0, 3)), (Location::new(1, 0), Location::new(1, 1)), (Location::new(2, 0), Location::new(2, 1))]
+        );
+    }
+}
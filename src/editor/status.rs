@@ -0,0 +1,77 @@
+use crossterm::style::Color;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::buffer::Location;
+use super::line::Line;
+use super::mode::Mode;
+
+const MESSAGE_TIMEOUT: Duration = Duration::from_secs(5);
+pub const STATUS_FOREGROUND: Color = Color::Black;
+pub const STATUS_BACKGROUND: Color = Color::Grey;
+pub const ERROR_FOREGROUND: Color = Color::Red;
+
+// A notification on the message line, gone after a few seconds
+#[derive(Clone, Debug)]
+pub struct Message {
+    pub text: String,
+    pub error: bool,
+    shown_at: Instant,
+}
+
+impl Message {
+    pub fn info(text: impl Into<String>) -> Self {
+        Self { text: text.into(), error: false, shown_at: Instant::now() }
+    }
+
+    pub fn error(text: impl Into<String>) -> Self {
+        Self { text: text.into(), error: true, shown_at: Instant::now() }
+    }
+
+    // How long it stays up after `now`, zero once it has expired
+    pub fn remaining(&self, now: Instant) -> Duration {
+        MESSAGE_TIMEOUT.saturating_sub(now.saturating_duration_since(self.shown_at))
+    }
+
+    pub fn expired(&self, now: Instant) -> bool {
+        self.remaining(now).is_zero()
+    }
+}
+
+// The status bar padded to `width`: mode, file name and dirty flag on the left, the cursor's
+// 1-based line and column on the right. The left side gives way when they don't both fit
+pub fn status_text(mode: Mode, filename: Option<&Path>, modified: bool, cursor: Location, width: usize) -> String {
+    let name = filename
+        .and_then(Path::file_name)
+        .map_or_else(|| "[No Name]".to_string(), |name| name.to_string_lossy().into_owned());
+    let left = format!(" {}  {name}{}", mode.name(), if modified { " [+]" } else { "" });
+    let right = format!(" {}:{} ", cursor.line + 1, cursor.col + 1);
+    let right_width = right.len().min(width);
+    let left = Line::new(left).visible(0, width - right_width);
+    let padding = width.saturating_sub(Line::new(left.clone()).width() + right_width);
+    format!("{left}{}{}", " ".repeat(padding), &right[right.len() - right_width..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::selection::SelectionKind;
+
+    #[test]
+    fn status_text_fills_the_width() {
+        let path = Path::new("/tmp/notes.txt");
+        let cursor = Location { line: 11, col: 4 };
+        assert_eq!(status_text(Mode::Normal, Some(path), true, cursor, 30), " NORMAL  notes.txt [+]   12:5 ");
+        assert_eq!(status_text(Mode::Visual(SelectionKind::Line), None, false, cursor, 30), " V-LINE  [No Name]       12:5 ");
+        assert_eq!(status_text(Mode::Insert, Some(path), false, cursor, 12), " INSER 12:5 ");
+        assert_eq!(status_text(Mode::Insert, Some(path), false, cursor, 3), ":5 ");
+    }
+
+    #[test]
+    fn messages_expire() {
+        let message = Message::info("written");
+        assert!(!message.expired(message.shown_at));
+        assert_eq!(message.remaining(message.shown_at + Duration::from_secs(2)), Duration::from_secs(3));
+        assert!(message.expired(message.shown_at + MESSAGE_TIMEOUT));
+    }
+}
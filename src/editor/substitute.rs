@@ -0,0 +1,332 @@
+use regex::{Regex, RegexBuilder};
+use std::fmt::Write;
+use std::rc::Rc;
+
+use super::buffer::Location;
+use super::document::Document;
+
+// Lines a `:s` command works on, before it is resolved against the cursor and the selection
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Range {
+    CurrentLine,
+    Whole,     // %
+    Selection, // '<,'> as put on the command line by `:` in visual mode
+    Lines(usize, usize), // 1-based and inclusive, like `:3,7`
+}
+
+// A parsed `:[range]s/pattern/replacement/[flags]`
+#[derive(Debug, PartialEq, Eq)]
+pub struct Command {
+    pub range: Range,
+    pub pattern: String,
+    pub replacement: String, // in the syntax of `regex::Captures::expand`
+    pub global: bool,        // g: every match on a line, not only the first
+    pub confirm: bool,       // c: ask before each replacement
+    pub ignore_case: bool,   // i
+}
+
+// None when the command line isn't a substitution at all
+pub fn parse(command_line: &str) -> Option<Result<Command, String>> {
+    let (range, rest) = parse_range(command_line);
+    let rest = rest.strip_prefix("substitute").or_else(|| rest.strip_prefix('s'))?;
+    let delimiter = rest.chars().next()?;
+    if delimiter.is_alphanumeric() || delimiter.is_whitespace() || delimiter == '\\' {
+        return None;
+    }
+    let mut parts = split_unescaped(&rest[delimiter.len_utf8()..], delimiter).into_iter();
+    let pattern = parts.next().unwrap_or_default();
+    let replacement = parts.next().unwrap_or_default();
+    let flags = parts.next().unwrap_or_default();
+    let mut command = Command {
+        range,
+        pattern,
+        replacement: expand_template(&replacement),
+        global: false,
+        confirm: false,
+        ignore_case: false,
+    };
+    for flag in flags.trim().chars() {
+        match flag {
+            'g' => command.global = true,
+            'c' => command.confirm = true,
+            'i' => command.ignore_case = true,
+            flag => return Some(Err(format!("Unknown flag: {flag}"))),
+        }
+    }
+    if command.pattern.is_empty() {
+        return Some(Err("Empty pattern".to_string()));
+    }
+    Some(Ok(command))
+}
+
+fn parse_range(command_line: &str) -> (Range, &str) {
+    if let Some(rest) = command_line.strip_prefix('%') {
+        return (Range::Whole, rest);
+    }
+    if let Some(rest) = command_line.strip_prefix("'<,'>") {
+        return (Range::Selection, rest);
+    }
+    let digits = |text: &str| text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let first_len = digits(command_line);
+    let Ok(first) = command_line[..first_len].parse::<usize>() else {
+        return (Range::CurrentLine, command_line);
+    };
+    let rest = &command_line[first_len..];
+    if let Some(after_comma) = rest.strip_prefix(',') {
+        let last_len = digits(after_comma);
+        if let Ok(last) = after_comma[..last_len].parse::<usize>() {
+            return (Range::Lines(first.min(last), first.max(last)), &after_comma[last_len..]);
+        }
+    }
+    (Range::Lines(first, first), rest)
+}
+
+// Split on the delimiter, `\` followed by the delimiter stands for the delimiter itself
+fn split_unescaped(text: &str, delimiter: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        let part = parts.last_mut().expect("parts starts non-empty");
+        match c {
+            '\\' => match chars.next() {
+                Some(next) if next == delimiter => part.push(next),
+                Some(next) => {
+                    part.push('\\');
+                    part.push(next);
+                }
+                None => part.push('\\'),
+            },
+            c if c == delimiter => parts.push(String::new()),
+            c => part.push(c),
+        }
+    }
+    parts
+}
+
+// Vim style escapes in the replacement to what Captures::expand takes: `\1` and `&` for groups,
+// `\n` and `\t` for a line break and a tab. `$1` and `${name}` work as they are
+fn expand_template(replacement: &str) -> String {
+    let mut template = String::new();
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(digit @ '0'..='9') => {
+                    let _ = write!(template, "${{{digit}}}");
+                },
+                Some('n') => template.push('\n'),
+                Some('t') => template.push('\t'),
+                Some('&') => template.push('&'),
+                Some('$') => template.push_str("$$"),
+                Some(other) => template.push(other),
+                None => template.push('\\'),
+            },
+            '&' => template.push_str("${0}"),
+            c => template.push(c),
+        }
+    }
+    template
+}
+
+// A match waiting to be replaced, offsets count chars in the document
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Match {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub replacement: String,
+}
+
+// A substitution going through its range front to back, one match at a time when confirming
+pub struct Substitution {
+    regex: Regex,
+    replacement: String,
+    global: bool,
+    pub pattern: String,
+    text: Rc<str>, // the document's text when the substitution started, searched throughout
+    added: usize,   // bytes replacements have put into the document so far
+    removed: usize, // and taken out of it, together they map what is past them back into `text`
+    position: usize, // where the search goes on from
+    end: usize,      // matches have to end by here, edits move it
+    last_line: Option<usize>, // line of the last match, without g the rest of it is skipped
+    last_replaced_line: Option<usize>,
+    pub count: usize,
+    pub lines: usize,
+    pub current: Option<Match>, // the match the confirm prompt is asking about
+}
+
+impl Substitution {
+    // Matches of `command` in the chars `[start, end)` of the document
+    pub fn new(command: &Command, document: &Document, start: usize, end: usize) -> Result<Self, String> {
+        let regex = RegexBuilder::new(&command.pattern)
+            .case_insensitive(command.ignore_case)
+            .multi_line(true)
+            .build()
+            .map_err(|err| format!("Invalid pattern: {}", err.to_string().lines().last().unwrap_or_default().trim()))?;
+        Ok(Self {
+            regex,
+            replacement: command.replacement.clone(),
+            global: command.global,
+            pattern: command.pattern.clone(),
+            text: document.haystack(),
+            added: 0,
+            removed: 0,
+            position: start,
+            end,
+            last_line: None,
+            last_replaced_line: None,
+            count: 0,
+            lines: 0,
+            current: None,
+        })
+    }
+
+    // The first match at or after `position`. Everything from the last replacement on is still as
+    // it was in `text`, only moved by what the replacements added and removed
+    fn find(&self, document: &Document, mut position: usize, last_line: Option<usize>) -> Option<Match> {
+        let to_document = |byte: usize| document.byte_to_char(byte + self.added - self.removed);
+        loop {
+            if position > self.end || position > document.len_chars() {
+                return None;
+            }
+            let captures = self.regex.captures_at(&self.text, document.char_to_byte(position) + self.removed - self.added)?;
+            let found = captures.get(0)?;
+            let (start, end) = (to_document(found.start()), to_document(found.end()));
+            let line = document.char_to_location(start).line;
+            // An empty match right at the end only counts at the end of the document, anywhere
+            // else that spot belongs to what comes after the range
+            if start > self.end || (start == self.end && (start < end || self.end < document.len_chars())) {
+                return None;
+            }
+            if end > self.end {
+                position = start + 1;
+            } else if !self.global && last_line == Some(line) {
+                if line == document.last_line() {
+                    return None;
+                }
+                position = document.location_to_char(Location { line: line + 1, col: 0 });
+            } else {
+                let mut replacement = String::new();
+                captures.expand(&self.replacement, &mut replacement);
+                return Some(Match { start, end, line, replacement });
+            }
+        }
+    }
+
+    // Where to look after a match, an empty one has to be stepped over
+    fn after(found: &Match, replaced_len: usize) -> usize {
+        found.start + replaced_len + usize::from(found.start == found.end)
+    }
+
+    // Look for the next match to confirm, None when the range is done
+    pub fn advance(&mut self, document: &Document) -> Option<&Match> {
+        self.current = self.find(document, self.position, self.last_line);
+        self.current.as_ref()
+    }
+
+    // Skip the match being confirmed
+    pub fn skip(&mut self) {
+        if let Some(found) = self.current.take() {
+            self.position = Self::after(&found, found.end - found.start);
+            self.last_line = Some(found.line);
+        }
+    }
+
+    // Replace the match being confirmed, returning where the replacement ends
+    pub fn replace_current(&mut self, document: &mut Document, cursor: Location) -> Option<Location> {
+        let found = self.current.take()?;
+        self.count_replaced(std::slice::from_ref(&found));
+        self.removed += document.char_to_byte(found.end) - document.char_to_byte(found.start);
+        self.added += found.replacement.len();
+        let (from, to) = (document.char_to_location(found.start), document.char_to_location(found.end));
+        let end = document.replace_range(from, to, &found.replacement, cursor);
+        let len = found.replacement.chars().count();
+        self.end = self.end + len - (found.end - found.start);
+        self.position = Self::after(&found, len);
+        self.last_line = Some(found.line);
+        Some(end)
+    }
+
+    // Replace every match left in the range, returning where the last one starts
+    pub fn replace_rest(&mut self, document: &mut Document, cursor: Location) -> Option<Location> {
+        let mut found = Vec::new();
+        let (mut position, mut last_line) = (self.position, self.last_line);
+        while let Some(next) = self.find(document, position, last_line) {
+            position = Self::after(&next, next.end - next.start);
+            last_line = Some(next.line);
+            found.push(next);
+        }
+        let (last, before) = found.split_last()?;
+        self.count_replaced(&found);
+        // Back to front, so replacing one doesn't move the ones before it
+        for next in found.iter().rev() {
+            let (from, to) = (document.char_to_location(next.start), document.char_to_location(next.end));
+            document.replace_range(from, to, &next.replacement, cursor);
+        }
+        self.current = None;
+        self.position = self.end;
+        let removed: usize = before.iter().map(|m| m.end - m.start).sum();
+        let inserted: usize = before.iter().map(|m| m.replacement.chars().count()).sum();
+        Some(document.char_to_location(last.start - removed + inserted))
+    }
+
+    fn count_replaced(&mut self, replaced: &[Match]) {
+        for found in replaced {
+            if self.last_replaced_line != Some(found.line) {
+                self.lines += 1;
+            }
+            self.last_replaced_line = Some(found.line);
+        }
+        self.count += replaced.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replace(text: &str, command_line: &str) -> String {
+        let mut document = Document::from_text(text);
+        let command = parse(command_line).unwrap().unwrap();
+        let mut substitution = Substitution::new(&command, &document, 0, document.len_chars()).unwrap();
+        substitution.replace_rest(&mut document, Location::default());
+        document.text()
+    }
+
+    #[test]
+    fn parses_ranges_flags_and_replacements() {
+        let command = parse(r"'<,'>s#a\#b#\1-&-\&\n#gi").unwrap().unwrap();
+        assert_eq!(command.range, Range::Selection);
+        assert_eq!(command.pattern, "a#b");
+        assert_eq!(command.replacement, "${1}-${0}-&\n");
+        assert!(command.global && command.ignore_case && !command.confirm);
+        assert_eq!(parse("3,1substitute/a/b").unwrap().unwrap().range, Range::Lines(1, 3));
+        assert_eq!(parse("s/a/b/x"), Some(Err("Unknown flag: x".to_string())));
+        assert_eq!(parse("s//b/"), Some(Err("Empty pattern".to_string())));
+        assert_eq!(parse("set"), None);
+        assert_eq!(parse("w"), None);
+    }
+
+    #[test]
+    fn replaces_first_or_every_match_per_line() {
+        assert_eq!(replace("aaa\naa", "%s/a/b/"), "baa\nba");
+        assert_eq!(replace("aaa\naa", "%s/a/b/g"), "bbb\nbb");
+        assert_eq!(replace("ab", "%s/x*/-/g"), "-a-b-");
+        assert_eq!(replace("k=v\nx=y", r"%s/(\w)=(\w)/$2=$1/"), "v=k\ny=x");
+    }
+
+    #[test]
+    fn confirming_keeps_finding_matches_as_the_text_changes() {
+        let mut document = Document::from_text("a a\nxa");
+        let command = parse("%s/a/éé\\n/gc").unwrap().unwrap();
+        let mut substitution = Substitution::new(&command, &document, 0, document.len_chars()).unwrap();
+        assert_eq!(substitution.advance(&document).map(|found| found.start), Some(0));
+        substitution.replace_current(&mut document, Location::default());
+        assert_eq!(substitution.advance(&document).map(|found| (found.start, found.line)), Some((4, 1)));
+        substitution.skip();
+        assert_eq!(substitution.advance(&document).map(|found| (found.start, found.line)), Some((7, 2)));
+        substitution.replace_current(&mut document, Location::default());
+        assert_eq!(substitution.advance(&document), None);
+        assert_eq!(document.text(), "éé\n a\nxéé\n");
+    }
+}
@@ -0,0 +1,156 @@
+use crossterm::style::Color;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use super::{Position, Size};
+
+// A screen cell, the cell after a wide grapheme holds an empty symbol
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cell {
+    pub symbol: String,
+    pub foreground: Color,
+    pub background: Color,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self { symbol: " ".to_string(), foreground: Color::Reset, background: Color::Reset }
+    }
+}
+
+// A grid of cells drawn like a terminal, with a cursor and the colors the next print uses.
+// The editor draws a whole screen into one before any of it reaches the terminal
+#[derive(Clone)]
+pub struct Frame {
+    size: Size,
+    cells: Vec<Cell>,
+    pub cursor: Position,
+    pub cursor_visible: bool, // false leaves the cursor hidden, when what it points at is scrolled away
+    foreground: Color,
+    background: Color,
+}
+
+impl Frame {
+    pub fn new(size: Size) -> Self {
+        Self {
+            size,
+            cells: vec![Cell::default(); size.width as usize * size.height as usize],
+            cursor: Position::default(),
+            cursor_visible: true,
+            foreground: Color::Reset,
+            background: Color::Reset,
+        }
+    }
+
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    pub fn cell(&self, position: Position) -> Option<&Cell> {
+        self.index(position).map(|idx| &self.cells[idx])
+    }
+
+    fn cell_mut(&mut self, position: Position) -> Option<&mut Cell> {
+        self.index(position).map(|idx| &mut self.cells[idx])
+    }
+
+    fn index(&self, position: Position) -> Option<usize> {
+        (position.x < self.size.width && position.y < self.size.height)
+            .then(|| position.y as usize * self.size.width as usize + position.x as usize)
+    }
+
+    // Blank every cell, the cursor and colors stay
+    pub fn clear(&mut self) {
+        self.cells.fill(Cell::default());
+    }
+
+    // Blank the cursor's row
+    pub fn clear_line(&mut self) {
+        let y = self.cursor.y;
+        for x in 0..self.size.width {
+            if let Some(cell) = self.cell_mut(Position { x, y }) {
+                *cell = Cell::default();
+            }
+        }
+    }
+
+    pub fn move_cursor_to(&mut self, position: Position) {
+        self.cursor = position;
+    }
+
+    pub fn set_foreground_color(&mut self, color: Color) {
+        self.foreground = color;
+    }
+
+    pub fn set_background_color(&mut self, color: Color) {
+        self.background = color;
+    }
+
+    // Write at the cursor and move it along, anything past the edges is dropped
+    pub fn print(&mut self, string: &str) {
+        for grapheme in string.graphemes(true) {
+            match grapheme {
+                "\r" => self.cursor.x = 0,
+                "\n" | "\r\n" => self.cursor.y = self.cursor.y.saturating_add(1),
+                _ => {
+                    let (position, foreground, background) = (self.cursor, self.foreground, self.background);
+                    let width = u16::try_from(grapheme.width()).unwrap_or(u16::MAX);
+                    if width == 0 {
+                        // Zero width graphemes combine with the previous cell
+                        let previous = Position { x: position.x.saturating_sub(1), ..position };
+                        if let Some(cell) = self.cell_mut(previous) {
+                            cell.symbol.push_str(grapheme);
+                        }
+                        continue;
+                    }
+                    if let Some(cell) = self.cell_mut(position) {
+                        *cell = Cell { symbol: grapheme.to_string(), foreground, background };
+                    }
+                    for offset in 1..width {
+                        let covered = Position { x: position.x.saturating_add(offset), ..position };
+                        if let Some(cell) = self.cell_mut(covered) {
+                            *cell = Cell { symbol: String::new(), foreground, background };
+                        }
+                    }
+                    self.cursor.x = self.cursor.x.saturating_add(width);
+                }
+            }
+        }
+    }
+
+    // Cells that differ from `shown`, all of them when there is nothing shown of the same size.
+    // The cells a wide grapheme covers are left out, printing the grapheme takes care of them
+    pub fn changes<'a>(&'a self, shown: Option<&'a Frame>) -> impl Iterator<Item = (Position, &'a Cell)> + 'a {
+        let shown = shown.filter(|shown| shown.size == self.size);
+        let width = self.size.width.max(1);
+        self.cells.iter().enumerate().filter_map(move |(idx, cell)| {
+            let changed = shown.is_none_or(|shown| shown.cells[idx] != *cell);
+            let position = Position { x: to_u16(idx % width as usize), y: to_u16(idx / width as usize) };
+            (changed && !cell.symbol.is_empty()).then_some((position, cell))
+        })
+    }
+}
+
+fn to_u16(n: usize) -> u16 {
+    u16::try_from(n).unwrap_or(u16::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_changed_cells_are_listed() {
+        let size = Size { height: 2, width: 4 };
+        let mut shown = Frame::new(size);
+        shown.print("ab");
+        let mut frame = shown.clone();
+        assert_eq!(frame.changes(Some(&shown)).count(), 0);
+        frame.move_cursor_to(Position { x: 1, y: 1 });
+        frame.print("日");
+        let changed: Vec<_> = frame.changes(Some(&shown)).map(|(position, cell)| (position, cell.symbol.as_str())).collect();
+        assert_eq!(changed, [(Position { x: 1, y: 1 }, "日")]);
+        assert_eq!(frame.changes(None).count(), 7);
+        assert_eq!(frame.changes(Some(&Frame::new(Size { height: 1, width: 4 }))).count(), 7);
+    }
+}
@@ -0,0 +1,203 @@
+use crossterm::cursor::SetCursorStyle;
+use crossterm::event::Event;
+use crossterm::style::Color;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind};
+use std::rc::Rc;
+use std::time::Duration;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{Cell, Frame, Position, Size, Terminal};
+
+struct Screen {
+    frame: Frame,
+    cursor_visible: bool,
+    cursor_style: SetCursorStyle,
+    clipboard: Option<String>,
+    events: VecDeque<Event>,
+    printed: usize, // graphemes printed, to tell how much drawing costs
+    frames: usize,  // synchronized updates ended
+}
+
+// Headless terminal keeping a virtual grid of cells, clones share the same screen so a test
+// can hand one to the editor and inspect the other
+#[derive(Clone)]
+pub struct MemoryTerminal {
+    screen: Rc<RefCell<Screen>>,
+}
+
+impl MemoryTerminal {
+    #[must_use]
+    pub fn new(size: Size) -> Self {
+        Self {
+            screen: Rc::new(RefCell::new(Screen {
+                frame: Frame::new(size),
+                cursor_visible: true,
+                cursor_style: SetCursorStyle::DefaultUserShape,
+                clipboard: None,
+                events: VecDeque::new(),
+                printed: 0,
+                frames: 0,
+            })),
+        }
+    }
+
+    // Change the screen size like a resized window would, what was on it is gone. The editor
+    // hears about it through a queued or passed Event::Resize
+    pub fn set_size(&self, size: Size) {
+        self.screen.borrow_mut().frame = Frame::new(size);
+    }
+
+    // Queue an event for the editor to read
+    pub fn push_event(&self, event: Event) {
+        self.screen.borrow_mut().events.push_back(event);
+    }
+
+    // Text of a screen row with trailing blanks trimmed
+    #[must_use]
+    pub fn row(&self, y: u16) -> String {
+        let screen = self.screen.borrow();
+        let row: String = (0..screen.frame.size().width)
+            .filter_map(|x| screen.frame.cell(Position { x, y }))
+            .map(|cell| cell.symbol.as_str())
+            .collect();
+        row.trim_end().to_string()
+    }
+
+    #[must_use]
+    pub fn rows(&self) -> Vec<String> {
+        let height = self.screen.borrow().frame.size().height;
+        (0..height).map(|y| self.row(y)).collect()
+    }
+
+    #[must_use]
+    pub fn cell(&self, position: Position) -> Cell {
+        self.screen.borrow().frame.cell(position).cloned().unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn cursor(&self) -> Position {
+        self.screen.borrow().frame.cursor
+    }
+
+    #[must_use]
+    pub fn cursor_visible(&self) -> bool {
+        self.screen.borrow().cursor_visible
+    }
+
+    #[must_use]
+    pub fn cursor_style(&self) -> SetCursorStyle {
+        self.screen.borrow().cursor_style
+    }
+
+    // Graphemes printed so far
+    #[must_use]
+    pub fn printed(&self) -> usize {
+        self.screen.borrow().printed
+    }
+
+    // Synchronized updates ended so far, one per frame the editor draws
+    #[must_use]
+    pub fn frames(&self) -> usize {
+        self.screen.borrow().frames
+    }
+
+    // Text last sent to the clipboard
+    #[must_use]
+    pub fn clipboard(&self) -> Option<String> {
+        self.screen.borrow().clipboard.clone()
+    }
+}
+
+impl Terminal for MemoryTerminal {
+    fn initialize(&mut self) -> Result<(), Error> {
+        self.clear_screen()
+    }
+
+    fn terminate(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn size(&self) -> Result<Size, Error> {
+        Ok(self.screen.borrow().frame.size())
+    }
+
+    fn read_event(&mut self) -> Result<Event, Error> {
+        self.screen
+            .borrow_mut()
+            .events
+            .pop_front()
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "no more queued events"))
+    }
+
+    // Queued events are there at once, nothing else ever arrives
+    fn poll_event(&mut self, _: Duration) -> Result<bool, Error> {
+        Ok(!self.screen.borrow().events.is_empty())
+    }
+
+    fn clear_screen(&mut self) -> Result<(), Error> {
+        self.screen.borrow_mut().frame.clear();
+        Ok(())
+    }
+
+    fn clear_line(&mut self) -> Result<(), Error> {
+        self.screen.borrow_mut().frame.clear_line();
+        Ok(())
+    }
+
+    fn move_cursor_to(&mut self, position: Position) -> Result<(), Error> {
+        self.screen.borrow_mut().frame.move_cursor_to(position);
+        Ok(())
+    }
+
+    fn hide_cursor(&mut self) -> Result<(), Error> {
+        self.screen.borrow_mut().cursor_visible = false;
+        Ok(())
+    }
+
+    fn show_cursor(&mut self) -> Result<(), Error> {
+        self.screen.borrow_mut().cursor_visible = true;
+        Ok(())
+    }
+
+    fn set_cursor_style(&mut self, style: SetCursorStyle) -> Result<(), Error> {
+        self.screen.borrow_mut().cursor_style = style;
+        Ok(())
+    }
+
+    fn set_foreground_color(&mut self, color: Color) -> Result<(), Error> {
+        self.screen.borrow_mut().frame.set_foreground_color(color);
+        Ok(())
+    }
+
+    fn set_background_color(&mut self, color: Color) -> Result<(), Error> {
+        self.screen.borrow_mut().frame.set_background_color(color);
+        Ok(())
+    }
+
+    fn print(&mut self, string: &str) -> Result<(), Error> {
+        let mut screen = self.screen.borrow_mut();
+        screen.printed += string.graphemes(true).count();
+        screen.frame.print(string);
+        Ok(())
+    }
+
+    fn begin_synchronized_update(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn end_synchronized_update(&mut self) -> Result<(), Error> {
+        self.screen.borrow_mut().frames += 1;
+        Ok(())
+    }
+
+    fn copy_to_clipboard(&mut self, text: &str) -> Result<(), Error> {
+        self.screen.borrow_mut().clipboard = Some(text.to_string());
+        Ok(())
+    }
+
+    fn execute(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
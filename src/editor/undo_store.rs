@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io::{Error, Write};
+use std::path::{Path, PathBuf};
+
+use super::buffer::Buffer;
+use super::file_io;
+use super::history::{History, Transaction};
+
+const FORMAT_VERSION: u32 = 1;
+
+// Undo history as written to the cache dir, one file per edited path
+#[derive(Serialize, Deserialize)]
+struct StoredHistory {
+    version: u32,
+    path: PathBuf,
+    content_hash: u64,
+    undo: Vec<Transaction>,
+    redo: Vec<Transaction>,
+}
+
+// $XDG_CACHE_HOME/crab/undo, falling back to ~/.cache/crab/undo
+pub fn default_dir() -> Option<PathBuf> {
+    let cache = env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(cache.join("crab").join("undo"))
+}
+
+// History file for a document, keyed by its absolute path
+pub fn history_path(dir: &Path, document_path: &Path) -> PathBuf {
+    let mut hasher = Fnv64::default();
+    hasher.update(absolute(document_path).to_string_lossy().as_bytes());
+    dir.join(format!("{:016x}.json", hasher.finish()))
+}
+
+// Hash of the text as it is written to disk
+pub fn content_hash(buffer: &dyn Buffer) -> u64 {
+    let mut hasher = Fnv64::default();
+    // Writing into the hasher can't fail
+    let _ = buffer.write_to(&mut hasher);
+    hasher.finish()
+}
+
+// Load the history saved for this document, anything stale, foreign or unreadable is discarded
+pub fn load(history_file: &Path, document_path: &Path, content_hash: u64) -> Option<History> {
+    let contents = fs::read(history_file).ok()?;
+    let stored: StoredHistory = serde_json::from_slice(&contents).ok()?;
+    let matches = stored.version == FORMAT_VERSION
+        && stored.content_hash == content_hash
+        && stored.path == absolute(document_path);
+    if !matches {
+        // The file changed since the history was written, replaying it would corrupt the text
+        let _ = fs::remove_file(history_file);
+        return None;
+    }
+    Some(History::from_stacks(stored.undo, stored.redo))
+}
+
+// Save the history belonging to the text with the given hash
+pub fn store(history_file: &Path, document_path: &Path, content_hash: u64, history: &History) -> Result<(), Error> {
+    let (undo, redo) = history.stacks();
+    let stored = StoredHistory {
+        version: FORMAT_VERSION,
+        path: absolute(document_path),
+        content_hash,
+        undo: undo.to_vec(),
+        redo: redo.to_vec(),
+    };
+    if let Some(dir) = history_file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    file_io::write_atomic(history_file, |writer| Ok(serde_json::to_writer(writer, &stored)?))
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+// FNV-1a, stable across builds unlike the std hashers
+struct Fnv64(u64);
+
+impl Default for Fnv64 {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv64 {
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Write for Fnv64 {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
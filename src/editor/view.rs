@@ -0,0 +1,501 @@
+use crossterm::style::Color;
+use std::rc::Rc;
+
+use super::buffer::Location;
+use super::document::Document;
+use super::gutter::Gutter;
+use super::line::Line;
+use super::terminal::{Frame, Position, Size};
+use super::wrap::SoftWrap;
+
+const SELECTION_BACKGROUND: Color = Color::DarkGrey;
+const MATCH_BACKGROUND: Color = Color::DarkYellow;
+const CLIP_FOREGROUND: Color = Color::DarkGrey;
+const WRAP_FOREGROUND: Color = Color::DarkGrey;
+pub const SIDE_SCROLL_MARGIN: usize = 5;
+
+// Buffer lengths are usize while screen coordinates are u16
+pub fn to_u16(n: usize) -> u16 {
+    u16::try_from(n).unwrap_or(u16::MAX)
+}
+
+// Top left of the visible viewport, a buffer line and a screen column. When wrapping, the
+// viewport can start partway down a line, `row` is how many of its rows are above it
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScrollOffset {
+    pub line: usize,
+    pub x: usize,
+    pub row: usize,
+}
+
+// Cursor and viewport over a document
+pub struct View {
+    pub cursor: Location,            // (line,col) current pos in the buffer
+    pub scroll_offset: ScrollOffset, // top left of the visible viewport
+    pub size: Size,
+    pub gutter: Gutter,
+    pub side_scroll_margin: usize, // columns kept visible left and right of the cursor
+    pub soft_wrap: SoftWrap,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            cursor: Location::default(),
+            scroll_offset: ScrollOffset::default(),
+            size: Size::default(),
+            gutter: Gutter::default(),
+            side_scroll_margin: SIDE_SCROLL_MARGIN,
+            soft_wrap: SoftWrap::default(),
+        }
+    }
+}
+
+impl View {
+    // Up and down go by screen rows when wrapping
+    pub fn move_up(&mut self, document: &Document) {
+        if self.soft_wrap.enabled {
+            self.cursor = self.row_target(document, self.cursor, false, 1).unwrap_or(self.cursor);
+        } else if self.cursor.line > 0 {
+            self.move_to_line(document, self.cursor.line - 1);
+        }
+    }
+
+    // Returns false when the cursor is already on the last line
+    pub fn move_down(&mut self, document: &Document) -> bool {
+        if self.soft_wrap.enabled {
+            self.row_target(document, self.cursor, true, 1).map(|target| self.cursor = target).is_some()
+        } else if self.cursor.line < document.last_line() {
+            self.move_to_line(document, self.cursor.line + 1);
+            true
+        } else {
+            false
+        }
+    }
+
+    // Move to another line keeping the cursor in the same screen column
+    fn move_to_line(&mut self, document: &Document, line: usize) {
+        let x = document.line(self.cursor.line).col_to_x(self.cursor.col);
+        self.cursor = Location { line, col: document.line(line).x_to_col(x) };
+    }
+
+    pub fn move_left(&mut self, document: &Document) {
+        if self.cursor.col > 0 {
+            self.cursor.col = document.line(self.cursor.line).prev_boundary(self.cursor.col); // Move left
+        } else if self.cursor.line > 0 {
+            // If at the beginning of the line, move up to the last char of the previous line
+            self.cursor.line -= 1;
+            self.cursor.col = document.line_len(self.cursor.line);
+        }
+    }
+
+    pub fn move_right(&mut self, document: &Document) {
+        if self.cursor.col < document.line_len(self.cursor.line) {
+            self.cursor.col = document.line(self.cursor.line).next_boundary(self.cursor.col); // Move right
+        } else if self.cursor.line < document.last_line() {
+            // If at the end of the line, move down to the beginning of the next line
+            self.cursor.line += 1;
+            self.cursor.col = 0;
+        }
+    }
+
+    // The location `count` screen rows above or below `from` in the same screen column, as far
+    // as the document goes. None when `from` is on the first or last row already
+    pub fn row_target(&self, document: &Document, from: Location, down: bool, count: usize) -> Option<Location> {
+        let (from_row, start) = self.row_of(document, from);
+        let column = self.row_indent(from_row) + document.line(from.line).col_to_x(from.col) - start;
+        let (line, row) = self.step_rows(document, (from.line, from_row), down, count);
+        if (line, row) == (from.line, from_row) {
+            return None;
+        }
+        let rows = self.rows(document, line);
+        let x = rows[row] + column.saturating_sub(self.row_indent(row));
+        // Only a line's last row lets the cursor past its text, the others end where the next starts
+        let x = rows.get(row + 1).map_or(x, |next| x.min(next - 1));
+        Some(Location { line, col: document.line(line).x_to_col(x) })
+    }
+
+    // Screen rows a line takes, as the screen columns into it where each starts
+    fn rows(&self, document: &Document, line: usize) -> Rc<[usize]> {
+        self.soft_wrap.rows(document, line, self.text_width(document))
+    }
+
+    // Which of its line's rows a location is on, and the screen column that row starts at
+    fn row_of(&self, document: &Document, location: Location) -> (usize, usize) {
+        let x = document.line(location.line).col_to_x(location.col);
+        let rows = self.rows(document, location.line);
+        let row = rows.partition_point(|start| *start <= x).saturating_sub(1);
+        (row, rows[row])
+    }
+
+    // The (line, row) `count` screen rows down or up from `from`, stopping at either end
+    fn step_rows(&self, document: &Document, from: (usize, usize), down: bool, count: usize) -> (usize, usize) {
+        let (mut line, mut row) = from;
+        for _ in 0..count {
+            if down && row + 1 < self.rows(document, line).len() {
+                row += 1;
+            } else if down && line < document.last_line() {
+                (line, row) = (line + 1, 0);
+            } else if !down && row > 0 {
+                row -= 1;
+            } else if !down && line > 0 {
+                line -= 1;
+                row = self.rows(document, line).len() - 1;
+            } else {
+                break;
+            }
+        }
+        (line, row)
+    }
+
+    // Where a row's text starts, rows continuing a wrapped line begin with the indicator
+    fn row_indent(&self, row: usize) -> usize {
+        if row > 0 {
+            self.soft_wrap.indicator_width()
+        } else {
+            0
+        }
+    }
+
+    // Scroll so the cursor is visible and the viewport doesn't run past the end of the document
+    pub fn scroll_viewport(&mut self, document: &Document) {
+        if self.soft_wrap.enabled {
+            self.scroll_wrapped(document);
+            return;
+        }
+        let len_lines = document.len_lines();
+        let height = self.size.height as usize;
+        self.scroll_offset.row = 0;
+
+        if self.cursor.line < self.scroll_offset.line {
+            self.scroll_offset.line = self.cursor.line;
+        } else if height > 0 && self.cursor.line >= self.scroll_offset.line + height {
+            self.scroll_offset.line = self.cursor.line + 1 - height;
+        }
+
+        // Ensure the viewport doesn't exceed terminal size
+        if self.scroll_offset.line + height > len_lines {
+            self.scroll_offset.line = len_lines.saturating_sub(height);
+        }
+
+        // Sideways only as far as it takes to keep the margin around the cursor, which can't be
+        // more than half the width or the cursor would have nowhere to go
+        let width = self.text_width(document);
+        let margin = self.side_scroll_margin.min(width.saturating_sub(1) / 2);
+        let x = document.line(self.cursor.line).col_to_x(self.cursor.col);
+        if x < self.scroll_offset.x + margin {
+            self.scroll_offset.x = x.saturating_sub(margin);
+        } else if width > 0 && x + margin >= self.scroll_offset.x + width {
+            self.scroll_offset.x = x + margin + 1 - width;
+        }
+    }
+
+    // scroll_viewport counting screen rows instead of lines, nothing is ever out of view sideways
+    fn scroll_wrapped(&mut self, document: &Document) {
+        let height = self.size.height as usize;
+        let line = self.scroll_offset.line.min(document.last_line());
+        let mut top = (line, self.scroll_offset.row.min(self.rows(document, line).len() - 1));
+        let cursor = (self.cursor.line, self.row_of(document, self.cursor).0);
+        if cursor < top {
+            top = cursor;
+        } else if height > 0 {
+            top = top.max(self.step_rows(document, cursor, false, height - 1));
+        }
+        let last = (document.last_line(), self.rows(document, document.last_line()).len() - 1);
+        top = top.min(self.step_rows(document, last, false, height.saturating_sub(1)));
+        self.scroll_offset = ScrollOffset { line: top.0, x: 0, row: top.1 };
+    }
+
+    // Screen columns left for the text beside the gutter
+    pub fn text_width(&self, document: &Document) -> usize {
+        (self.size.width as usize).saturating_sub(self.gutter.width(document.len_lines()))
+    }
+
+    // Screen position of a buffer location given the current viewport
+    pub fn screen_position(&self, document: &Document, location: Location) -> Position {
+        let x = document.line(location.line).col_to_x(location.col);
+        let gutter = self.gutter.width(document.len_lines());
+        if !self.soft_wrap.enabled {
+            return Position {
+                x: to_u16(gutter + x.saturating_sub(self.scroll_offset.x)),
+                y: to_u16(location.line.saturating_sub(self.scroll_offset.line)),
+            };
+        }
+        let (row, start) = self.row_of(document, location);
+        // Locations above the viewport go on its top row and those below it on its bottom row
+        let (mut at, mut y) = ((self.scroll_offset.line, self.scroll_offset.row), 0);
+        while at < (location.line, row) && y + 1 < self.size.height as usize {
+            at = self.step_rows(document, at, true, 1);
+            y += 1;
+        }
+        let column = (self.row_indent(row) + x - start).min(self.text_width(document).saturating_sub(1));
+        Position { x: to_u16(gutter + column), y: to_u16(y) }
+    }
+
+    // Buffer location shown at a screen position, None below the text area. Clicks in the
+    // gutter go to the start of the row and clicks past the text to the nearest location
+    pub fn location_at(&self, document: &Document, position: Position) -> Option<Location> {
+        if position.y >= self.size.height {
+            return None;
+        }
+        let column = (position.x as usize).saturating_sub(self.gutter.width(document.len_lines()));
+        if !self.soft_wrap.enabled {
+            let line = (self.scroll_offset.line + position.y as usize).min(document.last_line());
+            let col = document.line(line).x_to_col(self.scroll_offset.x + column);
+            return Some(Location { line, col });
+        }
+        let top = (self.scroll_offset.line, self.scroll_offset.row);
+        let (line, row) = self.step_rows(document, top, true, position.y as usize);
+        let rows = self.rows(document, line);
+        let x = rows[row] + column.saturating_sub(self.row_indent(row));
+        let x = rows.get(row + 1).map_or(x, |next| x.min(next - 1));
+        Some(Location { line, col: document.line(line).x_to_col(x) })
+    }
+
+    // Whether a location is on screen, the wheel can scroll the cursor out of view
+    pub fn in_view(&self, document: &Document, location: Location) -> bool {
+        let height = self.size.height as usize;
+        if !self.soft_wrap.enabled {
+            let x = document.line(location.line).col_to_x(location.col);
+            return (self.scroll_offset.line..self.scroll_offset.line + height).contains(&location.line)
+                && (self.scroll_offset.x..self.scroll_offset.x + self.text_width(document)).contains(&x);
+        }
+        let top = (self.scroll_offset.line, self.scroll_offset.row);
+        let bottom = self.step_rows(document, top, true, height.saturating_sub(1));
+        let at = (location.line, self.row_of(document, location).0);
+        height > 0 && top <= at && at <= bottom
+    }
+
+    // Move the viewport by screen rows leaving the cursor where it is, no further than
+    // scroll_viewport would let it go
+    pub fn scroll_by(&mut self, document: &Document, down: bool, rows: usize) {
+        let height = self.size.height as usize;
+        if !self.soft_wrap.enabled {
+            let line = if down { self.scroll_offset.line + rows } else { self.scroll_offset.line.saturating_sub(rows) };
+            self.scroll_offset.line = line.min(document.len_lines().saturating_sub(height));
+            return;
+        }
+        let top = self.step_rows(document, (self.scroll_offset.line, self.scroll_offset.row), down, rows);
+        let last = (document.last_line(), self.rows(document, document.last_line()).len() - 1);
+        let top = top.min(self.step_rows(document, last, false, height.saturating_sub(1)));
+        self.scroll_offset = ScrollOffset { line: top.0, x: 0, row: top.1 };
+    }
+
+    // Draw the visible rows of the document, highlighting the selected and the matched
+    // `[start, end)` ranges, the selection on top
+    pub fn draw_rows(
+        &self,
+        document: &Document,
+        frame: &mut Frame,
+        selected: &[(Location, Location)],
+        matches: &[(Location, Location)],
+    ) {
+        if self.soft_wrap.enabled {
+            self.draw_wrapped_rows(document, frame, selected, matches);
+        } else {
+            self.draw_clipped_rows(document, frame, selected, matches);
+        }
+
+        // After drawing rows, move the cursor to the actual position
+        frame.move_cursor_to(self.screen_position(document, self.cursor));
+    }
+
+    // A line per row, cut to the width and scrolled sideways
+    fn draw_clipped_rows(
+        &self,
+        document: &Document,
+        frame: &mut Frame,
+        selected: &[(Location, Location)],
+        matches: &[(Location, Location)],
+    ) {
+        let start = self.scroll_offset.line;
+        let end = (start + self.size.height as usize).min(document.len_lines());
+        let text_width = self.text_width(document);
+
+        for y in start..end {
+            frame.move_cursor_to(Position { x: 0, y: to_u16(y - start) });
+            frame.clear_line();
+            self.gutter.draw(frame, y, self.cursor.line, document.len_lines());
+
+            let line = document.line(y);
+            let highlights = highlights(y, &line, selected, matches);
+            if highlights.is_empty() {
+                frame.print(&line.visible(self.scroll_offset.x, text_width));
+            } else {
+                draw_highlighted_line(&line, &highlights, self.scroll_offset.x, text_width, frame);
+            }
+            self.draw_clip_indicators(&line, to_u16(y - start), text_width, frame);
+        }
+
+        // Indicate rows past the end of the document
+        for y in end - start..self.size.height as usize {
+            frame.move_cursor_to(Position { x: 0, y: to_u16(y) });
+            frame.clear_line();
+            frame.print("~");
+        }
+    }
+
+    // Long lines go on over the rows below, after the wrap indicator
+    fn draw_wrapped_rows(
+        &self,
+        document: &Document,
+        frame: &mut Frame,
+        selected: &[(Location, Location)],
+        matches: &[(Location, Location)],
+    ) {
+        let text_width = self.text_width(document);
+        let mut at = Some((self.scroll_offset.line, self.scroll_offset.row));
+        for y in 0..self.size.height {
+            frame.move_cursor_to(Position { x: 0, y });
+            frame.clear_line();
+            let Some((line_idx, row)) = at else {
+                frame.print("~");
+                continue;
+            };
+            if row == 0 {
+                self.gutter.draw(frame, line_idx, self.cursor.line, document.len_lines());
+            } else {
+                self.gutter.draw_blank(frame, document.len_lines());
+                frame.set_foreground_color(WRAP_FOREGROUND);
+                frame.print(&self.soft_wrap.indicator);
+                frame.set_foreground_color(Color::Reset);
+            }
+
+            let line = document.line(line_idx);
+            let rows = self.rows(document, line_idx);
+            let start = rows[row];
+            let width = text_width.saturating_sub(self.row_indent(row));
+            let width = rows.get(row + 1).map_or(width, |next| width.min(next - start));
+            let highlights = highlights(line_idx, &line, selected, matches);
+            if highlights.is_empty() {
+                frame.print(&line.visible(start, width));
+            } else {
+                draw_highlighted_line(&line, &highlights, start, width, frame);
+            }
+            at = if row + 1 < rows.len() {
+                Some((line_idx, row + 1))
+            } else {
+                (line_idx < document.last_line()).then_some((line_idx + 1, 0))
+            };
+        }
+    }
+
+    // Mark the edges of a line that has text scrolled out of view on that side
+    fn draw_clip_indicators(&self, line: &Line, y: u16, width: usize, frame: &mut Frame) {
+        let left = self.size.width as usize - width; // where the text starts, right of the gutter
+        let clipped_left = self.scroll_offset.x > 0 && line.width() > 0;
+        let clipped_right = line.width() > self.scroll_offset.x + width;
+        if width == 0 || !(clipped_left || clipped_right) {
+            return;
+        }
+        frame.set_foreground_color(CLIP_FOREGROUND);
+        if clipped_left {
+            frame.move_cursor_to(Position { x: to_u16(left), y });
+            frame.print("<");
+        }
+        if clipped_right {
+            frame.move_cursor_to(Position { x: to_u16(left + width - 1), y });
+            frame.print(">");
+        }
+        frame.set_foreground_color(Color::Reset);
+    }
+}
+
+// Screen columns of a line to highlight and in what, the selection before the matches
+fn highlights(line_idx: usize, line: &Line, selected: &[(Location, Location)], matches: &[(Location, Location)]) -> Vec<((usize, usize), Color)> {
+    selected
+        .iter()
+        .filter_map(|&range| selected_x(line_idx, line, range).map(|x| (x, SELECTION_BACKGROUND)))
+        .chain(matches.iter().filter_map(|&range| selected_x(line_idx, line, range).map(|x| (x, MATCH_BACKGROUND))))
+        .collect()
+}
+
+// Print the screen columns `left..left + width` of a line in runs of one background, the first
+// highlight covering a screen column wins
+fn draw_highlighted_line(
+    line: &Line,
+    highlights: &[((usize, usize), Color)],
+    left: usize,
+    width: usize,
+    frame: &mut Frame,
+) {
+    let right = left + width;
+    let mut edges: Vec<usize> = highlights.iter().flat_map(|((start, end), _)| [*start, *end]).collect();
+    edges.extend([left, right]);
+    edges.retain(|x| (left..=right).contains(x));
+    edges.sort_unstable();
+    edges.dedup();
+    for run in edges.windows(2) {
+        let (start, end) = (run[0], run[1]);
+        let text = line.visible(start, end - start);
+        match highlights.iter().find(|((from, to), _)| (*from..*to).contains(&start)) {
+            Some((_, color)) => {
+                frame.set_background_color(*color);
+                // The line break is selected too, it shows as one cell past the end of the line
+                let padding = (end - start).saturating_sub(line.width().saturating_sub(start));
+                frame.print(&format!("{text}{}", " ".repeat(padding)));
+                frame.set_background_color(Color::Reset);
+            }
+            None => frame.print(&text),
+        }
+    }
+}
+
+// Screen columns of a line covered by the selection, None if the line has no part of it
+fn selected_x(line_idx: usize, line: &Line, (start, end): (Location, Location)) -> Option<(usize, usize)> {
+    if line_idx < start.line || line_idx > end.line || start == end {
+        return None;
+    }
+    let start_x = if line_idx == start.line { line.col_to_x(start.col) } else { 0 };
+    let end_x = if line_idx == end.line { line.col_to_x(end.col) } else { line.width() + 1 };
+    (start_x < end_x).then_some((start_x, end_x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(height: u16) -> View {
+        View { size: Size { height, width: 80 }, ..View::default() }
+    }
+
+    #[test]
+    fn moving_right_wraps_to_next_line() {
+        let mut document = Document::default();
+        document.insert_text(Location::default(), "ab\ncd");
+        let mut view = view(10);
+        view.cursor.col = 2;
+        view.move_right(&document);
+        assert_eq!(view.cursor, Location { line: 1, col: 0 });
+        view.move_left(&document);
+        assert_eq!(view.cursor, Location { line: 0, col: 2 });
+    }
+
+    #[test]
+    fn vertical_moves_keep_the_screen_column() {
+        let mut document = Document::default();
+        document.insert_text(Location::default(), "日本語\nabcdef\né");
+        let mut view = view(10);
+        view.cursor.col = 2;
+        view.move_down(&document);
+        assert_eq!(view.cursor, Location { line: 1, col: 4 });
+        view.move_up(&document);
+        assert_eq!(view.cursor, Location { line: 0, col: 2 });
+        view.move_down(&document);
+        view.move_down(&document);
+        assert_eq!(view.cursor, Location { line: 2, col: 1 });
+    }
+
+    #[test]
+    fn scrolling_follows_cursor() {
+        let mut document = Document::default();
+        document.insert_text(Location::default(), &"line\n".repeat(20));
+        let mut view = view(5);
+        view.cursor.line = 12;
+        view.scroll_viewport(&document);
+        assert_eq!(view.scroll_offset.line, 8);
+        view.cursor.line = 3;
+        view.scroll_viewport(&document);
+        assert_eq!(view.scroll_offset.line, 3);
+    }
+}
@@ -0,0 +1,98 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::document::{Document, LineChange};
+use super::line::Line;
+
+// Soft wrapping, long lines go on over as many screen rows as they need
+pub struct SoftWrap {
+    pub enabled: bool,
+    pub indicator: String, // printed at the start of every row but a line's first
+    pub at_words: bool,    // break rows after blanks instead of anywhere
+    cache: RefCell<RowCache>,
+}
+
+impl Default for SoftWrap {
+    fn default() -> Self {
+        Self { enabled: false, indicator: String::new(), at_words: true, cache: RefCell::default() }
+    }
+}
+
+// Row starts per line, good for as long as the line, the width and the settings are the same as
+// when they were worked out
+#[derive(Default)]
+struct RowCache {
+    settings: (usize, usize, bool), // width, indicator width and at_words
+    revision: u64,                  // of the document the rows were last brought up to
+    rows: HashMap<usize, Rc<[usize]>>,
+}
+
+impl RowCache {
+    // Forget the lines a change touched and move the ones after it to where they are now
+    fn follow(&mut self, change: &LineChange) {
+        let changed = change.line..=change.line + change.removed;
+        self.rows = std::mem::take(&mut self.rows)
+            .into_iter()
+            .filter(|(line, _)| !changed.contains(line))
+            .map(|(line, rows)| if line < change.line { (line, rows) } else { (line - change.removed + change.inserted, rows) })
+            .collect();
+    }
+}
+
+impl SoftWrap {
+    pub fn indicator_width(&self) -> usize {
+        Line::new(self.indicator.clone()).width()
+    }
+
+    // Screen columns into the line where each of its rows starts, a single row when not wrapping
+    pub fn rows(&self, document: &Document, line: usize, width: usize) -> Rc<[usize]> {
+        if !self.enabled {
+            return Rc::from([0]);
+        }
+        let settings = (width, self.indicator_width(), self.at_words);
+        let mut cache = self.cache.borrow_mut();
+        if cache.settings != settings {
+            cache.settings = settings;
+            cache.rows.clear();
+        } else if cache.revision != document.revision() {
+            match document.line_changes_since(cache.revision) {
+                Some(changes) => changes.for_each(|change| cache.follow(change)),
+                None => cache.rows.clear(),
+            }
+        }
+        cache.revision = document.revision();
+        let rest_width = width.saturating_sub(settings.1);
+        Rc::clone(
+            cache
+                .rows
+                .entry(line)
+                .or_insert_with(|| document.line(line).wrap(width, rest_width, self.at_words).into()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::buffer::Location;
+
+    #[test]
+    fn rows_are_cached_until_their_line_changes() {
+        let mut document = Document::from_text("aaaa bbbb\ncc");
+        let wrap = SoftWrap { enabled: true, indicator: "> ".to_string(), ..SoftWrap::default() };
+        let first = wrap.rows(&document, 0, 6);
+        assert_eq!(*first, [0, 5]);
+        assert!(Rc::ptr_eq(&first, &wrap.rows(&document, 0, 6)));
+        assert_eq!(*wrap.rows(&document, 0, 4), [0, 5, 7]);
+        let second = wrap.rows(&document, 1, 6);
+        assert_eq!(*wrap.rows(&document, 0, 6), [0, 5]);
+        document.insert_text(Location::default(), "x");
+        assert_eq!(*wrap.rows(&document, 0, 6), [0, 6]);
+        assert!(Rc::ptr_eq(&second, &wrap.rows(&document, 1, 6)));
+        document.insert_text(Location::default(), "y\n");
+        assert_eq!(*wrap.rows(&document, 0, 6), [0]);
+        assert!(Rc::ptr_eq(&second, &wrap.rows(&document, 2, 6)));
+        assert_eq!(*SoftWrap::default().rows(&document, 0, 4), [0]);
+    }
+}
@@ -0,0 +1,4 @@
+#![warn(clippy::all, clippy::pedantic)]
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
+mod editor;
+pub use editor::{Cell, ConfigError, CrosstermTerminal, Editor, LineNumbers, MemoryTerminal, Position, Sign, Size, Terminal};
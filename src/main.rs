@@ -1,10 +1,23 @@
 #![warn(clippy::all, clippy::pedantic)]
-mod editor;
-use editor::Editor;
+use crab::Editor;
 use std::env;
+use std::path::Path;
+use std::process;
 
 fn main()  {
     env::set_var("RUST_BACKTRACE", "1");
     let mut editor = Editor::default();
+    if let Some(config) = Editor::default_config_path() {
+        if let Err(err) = editor.load_config(&config) {
+            eprintln!("crab: {err}");
+            process::exit(1);
+        }
+    }
+    if let Some(path) = env::args().nth(1) {
+        if let Err(err) = editor.open(Path::new(&path)) {
+            eprintln!("crab: could not open {path}: {err}");
+            process::exit(1);
+        }
+    }
     editor.run();
-}
\ No newline at end of file
+}
@@ -0,0 +1,68 @@
+#![allow(dead_code)]
+use crab::{Editor, MemoryTerminal, Size};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn key(code: KeyCode) -> Event {
+    Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+pub fn ctrl(c: char) -> Event {
+    Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL))
+}
+
+pub fn alt(c: char) -> Event {
+    Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::ALT))
+}
+
+pub fn mouse(kind: MouseEventKind, column: u16, row: u16) -> Event {
+    Event::Mouse(MouseEvent { kind, column, row, modifiers: KeyModifiers::NONE })
+}
+
+// A path under the system temp dir unique to this test, removed up front
+pub fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("crab-test-{}-{name}", std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn undo_dir(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("crab-test-{}-{name}-undo", std::process::id()))
+}
+
+// Editor drawing to an in-memory terminal, with `contents` loaded from a temp file
+pub fn editor_with(name: &str, contents: &str, size: Size) -> (Editor, MemoryTerminal, PathBuf) {
+    let path = temp_path(name);
+    fs::write(&path, contents).unwrap();
+    let _ = fs::remove_dir_all(undo_dir(name));
+    let (editor, terminal) = open_editor(name, &path, size);
+    (editor, terminal, path)
+}
+
+// Editor on an existing file, keeping undo history in a per-test dir instead of the user's cache
+pub fn open_editor(name: &str, path: &Path, size: Size) -> (Editor, MemoryTerminal) {
+    let terminal = MemoryTerminal::new(size);
+    let mut editor = Editor::with_terminal(Box::new(terminal.clone()));
+    editor.set_undo_dir(Some(undo_dir(name)));
+    editor.open(path).unwrap();
+    editor.initialize().unwrap();
+    (editor, terminal)
+}
+
+pub fn type_text(editor: &mut Editor, text: &str) {
+    for c in text.chars() {
+        let event = match c {
+            '\n' => key(KeyCode::Enter),
+            c => key(KeyCode::Char(c)),
+        };
+        editor.evaluate_event(&event).unwrap();
+    }
+}
+
+// Rows of the text area, leaving out the status bar and the message line
+pub fn text_rows(terminal: &MemoryTerminal) -> Vec<String> {
+    let mut rows = terminal.rows();
+    rows.truncate(rows.len().saturating_sub(2));
+    rows
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use common::{ctrl, editor_with, key, temp_path, text_rows, type_text};
+use crab::Size;
+use crossterm::event::KeyCode;
+use std::fs;
+
+const SIZE: Size = Size { height: 7, width: 30 };
+
+fn config_error(name: &str, config: &str) -> String {
+    let (mut editor, _, _) = editor_with(name, "", SIZE);
+    let path = temp_path(&format!("{name}.toml"));
+    fs::write(&path, config).unwrap();
+    editor.load_config(&path).unwrap_err().to_string()
+}
+
+#[test]
+fn config_binds_chords_to_named_commands() {
+    let (mut editor, terminal, _) = editor_with("config-chords", "one\ntwo\nthree", SIZE);
+    let path = temp_path("config-chords.toml");
+    fs::write(&path, "[keys.normal]\n\"Ctrl+K Ctrl+D\" = \"delete-char\"\n\"g\" = \"last-line\"\n").unwrap();
+    editor.load_config(&path).unwrap();
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    // `g` alone replaces the default `g g`
+    type_text(&mut editor, "g");
+    editor.evaluate_event(&ctrl('k')).unwrap();
+    editor.evaluate_event(&ctrl('d')).unwrap();
+    assert_eq!(text_rows(&terminal)[..3], ["one", "two", "hree"]);
+}
+
+#[test]
+fn missing_config_keeps_the_defaults() {
+    let (mut editor, terminal, _) = editor_with("config-missing", "one", SIZE);
+    editor.load_config(&temp_path("config-missing.toml")).unwrap();
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    type_text(&mut editor, "x");
+    assert_eq!(terminal.row(0), "ne");
+}
+
+#[test]
+fn config_errors_name_the_offending_binding() {
+    assert_eq!(
+        config_error("config-unknown", "[keys.normal]\n\"Ctrl+K\" = \"frobnicate\"\n"),
+        "[keys.normal] \"Ctrl+K\": no command named `frobnicate`"
+    );
+    assert_eq!(
+        config_error("config-conflict", "[keys.insert]\n\"Ctrl+K\" = \"undo\"\n\"Ctrl+K Ctrl+C\" = \"redo\"\n"),
+        "[keys.insert] \"Ctrl+K Ctrl+C\" conflicts with \"Ctrl+K\", one of them could never be typed"
+    );
+    assert_eq!(
+        config_error("config-chord", "[keys.visual]\n\"Super+x\" = \"yank\"\n"),
+        "[keys.visual] \"Super+x\": unknown modifier `Super`"
+    );
+    assert!(config_error("config-mode", "[keys.replace]\n").starts_with("[keys.replace]: no such mode"));
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use common::{editor_with, key, temp_path, text_rows, type_text};
+use crab::{LineNumbers, Position, Sign, Size};
+use crossterm::event::KeyCode;
+use crossterm::style::Color;
+use std::fs;
+
+const SIZE: Size = Size { height: 6, width: 12 };
+
+#[test]
+fn numbers_shift_the_text_and_the_cursor() {
+    let (mut editor, terminal, _) = editor_with("gutter-absolute", "one\ntwo\nthree four five", SIZE);
+    editor.set_line_numbers(LineNumbers::Absolute);
+    type_text(&mut editor, "x");
+    assert_eq!(text_rows(&terminal), ["  1 xone", "  2 two", "  3 three f>", "~"]);
+    assert_eq!(terminal.cursor(), Position { x: 5, y: 0 });
+}
+
+#[test]
+fn relative_and_hybrid_numbers_follow_the_cursor() {
+    let (mut editor, terminal, _) = editor_with("gutter-relative", "a\nb\nc\nd", SIZE);
+    let path = temp_path("gutter-relative.toml");
+    fs::write(&path, "[editor]\nline-numbers = \"relative\"\n").unwrap();
+    editor.load_config(&path).unwrap();
+    editor.evaluate_event(&key(KeyCode::Down)).unwrap();
+    assert_eq!(text_rows(&terminal)[..4], ["  1 a", "  0 b", "  1 c", "  2 d"]);
+    editor.set_line_numbers(LineNumbers::Hybrid);
+    editor.evaluate_event(&key(KeyCode::Down)).unwrap();
+    assert_eq!(text_rows(&terminal)[..4], ["  2 a", "  1 b", "  3 c", "  1 d"]);
+    assert_eq!(terminal.cell(Position { x: 2, y: 2 }).foreground, Color::Yellow);
+}
+
+#[test]
+fn signs_get_a_column_of_their_own() {
+    let (mut editor, terminal, _) = editor_with("gutter-signs", "a\nb", SIZE);
+    editor.set_sign(Sign { group: "breakpoints".to_string(), line: 1, symbol: '●', color: Color::Red });
+    type_text(&mut editor, "x");
+    assert_eq!(text_rows(&terminal)[..2], ["  xa", "● b"]);
+    assert_eq!(terminal.cursor(), Position { x: 3, y: 0 });
+    assert_eq!(terminal.cell(Position { x: 0, y: 1 }).foreground, Color::Red);
+    editor.clear_signs("breakpoints");
+    type_text(&mut editor, "y");
+    assert_eq!(text_rows(&terminal)[..2], ["xya", "b"]);
+}
+
+#[test]
+fn unknown_settings_are_errors() {
+    let (mut editor, _, _) = editor_with("gutter-config", "", SIZE);
+    let path = temp_path("gutter-config.toml");
+    fs::write(&path, "[editor]\nline-numbers = \"roman\"\n").unwrap();
+    assert!(editor.load_config(&path).unwrap_err().to_string().contains("unknown variant `roman`"));
+}
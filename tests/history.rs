@@ -0,0 +1,88 @@
+mod common;
+
+use common::{ctrl, editor_with, key, open_editor, temp_path, text_rows, type_text};
+use crab::{Editor, MemoryTerminal, Position, Size};
+use crossterm::event::KeyCode;
+use crossterm::style::Color;
+use std::fs;
+
+const SIZE: Size = Size { height: 6, width: 20 };
+
+#[test]
+fn ctrl_z_undoes_typed_words_as_one_step() {
+    let (mut editor, terminal, _) = editor_with("undo-typing", "", SIZE);
+    type_text(&mut editor, "hello");
+    editor.evaluate_event(&key(KeyCode::Left)).unwrap();
+    type_text(&mut editor, "X");
+    assert_eq!(terminal.row(0), "hellXo");
+
+    editor.evaluate_event(&ctrl('z')).unwrap();
+    assert_eq!(terminal.row(0), "hello");
+    assert_eq!(terminal.cursor(), Position { x: 4, y: 0 });
+    editor.evaluate_event(&ctrl('z')).unwrap();
+    assert_eq!(terminal.row(0), "");
+    assert_eq!(terminal.cursor(), Position { x: 0, y: 0 });
+
+    editor.evaluate_event(&ctrl('y')).unwrap();
+    assert_eq!(terminal.row(0), "hello");
+    assert_eq!(terminal.cursor(), Position { x: 5, y: 0 });
+}
+
+#[test]
+fn history_survives_a_save() {
+    let (mut editor, terminal, _) = editor_with("undo-save", "one\n", SIZE);
+    editor.evaluate_event(&key(KeyCode::Enter)).unwrap();
+    editor.evaluate_event(&ctrl('s')).unwrap();
+    editor.evaluate_event(&ctrl('z')).unwrap();
+    assert_eq!(text_rows(&terminal), ["one", "", "~", "~"]);
+}
+
+#[test]
+fn undo_history_is_restored_after_reopening() {
+    let (mut editor, _, path) = editor_with("undo-reopen", "base\n", SIZE);
+    type_text(&mut editor, "more ");
+    editor.evaluate_event(&ctrl('s')).unwrap();
+    drop(editor);
+
+    let (mut editor, terminal) = open_editor("undo-reopen", &path, SIZE);
+    assert_eq!(terminal.row(0), "more base");
+    editor.evaluate_event(&ctrl('z')).unwrap();
+    assert_eq!(terminal.row(0), "base");
+}
+
+#[test]
+fn undo_history_is_discarded_when_the_file_changed() {
+    let (mut editor, _, path) = editor_with("undo-stale", "base\n", SIZE);
+    type_text(&mut editor, "more ");
+    editor.evaluate_event(&ctrl('s')).unwrap();
+    drop(editor);
+
+    fs::write(&path, "changed elsewhere\n").unwrap();
+    let (mut editor, terminal) = open_editor("undo-stale", &path, SIZE);
+    editor.evaluate_event(&ctrl('z')).unwrap();
+    assert_eq!(terminal.row(0), "changed elsewhere");
+}
+
+#[test]
+fn failing_to_save_the_history_shows_an_error() {
+    let path = temp_path("undo-unwritable");
+    fs::write(&path, "base\n").unwrap();
+    // A file where the undo directory should be
+    let blocker = temp_path("undo-unwritable-blocker");
+    fs::write(&blocker, "").unwrap();
+    let terminal = MemoryTerminal::new(Size { height: 6, width: 200 });
+    let mut editor = Editor::with_terminal(Box::new(terminal.clone()));
+    editor.set_undo_dir(Some(blocker.join("undo")));
+    editor.open(&path).unwrap();
+    editor.initialize().unwrap();
+    type_text(&mut editor, "more ");
+    editor.evaluate_event(&ctrl('s')).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "more base\n");
+    assert!(terminal.row(5).contains("written, but failed to save undo history"));
+    assert_eq!(terminal.cell(Position { x: 0, y: 5 }).foreground, Color::Red);
+    // :wq stays open to show it instead of quitting with the history lost
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    type_text(&mut editor, ":wq\n");
+    assert!(terminal.row(5).contains("written, but failed to save undo history"));
+    assert!(terminal.row(4).starts_with(" NORMAL"));
+}
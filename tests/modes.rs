@@ -0,0 +1,92 @@
+mod common;
+
+use common::{editor_with, key, text_rows, type_text};
+use crab::Size;
+use crossterm::event::KeyCode;
+use std::fs;
+
+const SIZE: Size = Size { height: 7, width: 30 };
+
+fn normal_mode(editor: &mut crab::Editor) {
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+}
+
+#[test]
+fn operators_compose_with_motions_and_counts() {
+    let (mut editor, terminal, _) = editor_with("modes-operators", "one two three four\nfive\nsix\nseven", SIZE);
+    normal_mode(&mut editor);
+    type_text(&mut editor, "dw");
+    assert_eq!(terminal.row(0), "two three four");
+    type_text(&mut editor, "2de");
+    assert_eq!(terminal.row(0), " four");
+    type_text(&mut editor, "j2dd");
+    assert_eq!(text_rows(&terminal)[..3], [" four", "seven", "~"]);
+    type_text(&mut editor, "u");
+    assert_eq!(text_rows(&terminal)[..4], [" four", "five", "six", "seven"]);
+}
+
+#[test]
+fn word_operators_stop_at_the_line_end() {
+    let (mut editor, terminal, _) = editor_with("modes-dw-eol", "ab cd\nef\n  gh ij\nkl", SIZE);
+    normal_mode(&mut editor);
+    type_text(&mut editor, "wdw");
+    assert_eq!(text_rows(&terminal)[..4], ["ab", "ef", "  gh ij", "kl"]);
+    // The next line's indent stays too
+    type_text(&mut editor, "j0dw");
+    assert_eq!(text_rows(&terminal)[..4], ["ab", "", "  gh ij", "kl"]);
+}
+
+#[test]
+fn change_enters_insert_mode() {
+    let (mut editor, terminal, _) = editor_with("modes-change", "hello world", SIZE);
+    normal_mode(&mut editor);
+    type_text(&mut editor, "wcwthere");
+    assert_eq!(terminal.row(0), "hello there");
+    normal_mode(&mut editor);
+    type_text(&mut editor, "0yyp");
+    assert_eq!(text_rows(&terminal)[..2], ["hello there", "hello there"]);
+}
+
+#[test]
+fn line_motions_jump_around_the_document() {
+    let (mut editor, terminal, _) = editor_with("modes-jumps", "a\nb\nc\nd", SIZE);
+    normal_mode(&mut editor);
+    type_text(&mut editor, "G");
+    assert_eq!(terminal.cursor().y, 3);
+    type_text(&mut editor, "gg");
+    assert_eq!(terminal.cursor().y, 0);
+    type_text(&mut editor, "3G$");
+    assert_eq!((terminal.cursor().x, terminal.cursor().y), (0, 2));
+    type_text(&mut editor, "ax");
+    assert_eq!(terminal.row(2), "cx");
+}
+
+#[test]
+fn command_line_runs_commands() {
+    let (mut editor, terminal, path) = editor_with("modes-command", "a\nb\nc", SIZE);
+    type_text(&mut editor, "z");
+    normal_mode(&mut editor);
+    type_text(&mut editor, ":3");
+    assert_eq!(terminal.row(6), ":3");
+    editor.evaluate_event(&key(KeyCode::Enter)).unwrap();
+    assert_eq!(terminal.cursor().y, 2);
+
+    type_text(&mut editor, ":nope");
+    editor.evaluate_event(&key(KeyCode::Enter)).unwrap();
+    assert_eq!(terminal.row(6), "Not an editor command: nope");
+
+    type_text(&mut editor, ":w");
+    editor.evaluate_event(&key(KeyCode::Enter)).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "za\nb\nc");
+}
+
+#[test]
+fn command_line_runs_registered_commands_by_name() {
+    let (mut editor, terminal, _) = editor_with("modes-registry", "one\ntwo", SIZE);
+    normal_mode(&mut editor);
+    type_text(&mut editor, ":open-line-below\nnew");
+    assert_eq!(text_rows(&terminal)[..3], ["one", "new", "two"]);
+    normal_mode(&mut editor);
+    type_text(&mut editor, ":help delete-char\n");
+    assert_eq!(terminal.row(6), "delete-char: Delete the charac");
+}
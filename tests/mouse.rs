@@ -0,0 +1,83 @@
+mod common;
+
+use common::{ctrl, editor_with, key, mouse, text_rows, type_text};
+use crab::{LineNumbers, Position, Size};
+use crossterm::event::{KeyCode, MouseButton, MouseEventKind};
+use std::fs;
+
+const SIZE: Size = Size { height: 6, width: 20 };
+const DOWN: MouseEventKind = MouseEventKind::Down(MouseButton::Left);
+const DRAG: MouseEventKind = MouseEventKind::Drag(MouseButton::Left);
+
+#[test]
+fn clicks_place_the_cursor_past_the_gutter_and_the_scroll() {
+    let contents: Vec<String> = (0..10).map(|n| format!("line {n}")).collect();
+    let (mut editor, terminal, _) = editor_with("mouse-click", &contents.join("\n"), SIZE);
+    editor.set_line_numbers(LineNumbers::Absolute);
+    for _ in 0..6 {
+        editor.evaluate_event(&key(KeyCode::Down)).unwrap();
+    }
+    assert_eq!(text_rows(&terminal)[0], "  4 line 3");
+    editor.evaluate_event(&mouse(DOWN, 7, 1)).unwrap();
+    assert_eq!(terminal.cursor(), Position { x: 7, y: 1 });
+    type_text(&mut editor, "X");
+    assert_eq!(text_rows(&terminal)[1], "  5 linXe 4");
+    // In the gutter the start of the line, past the text its end, below the text nothing
+    editor.evaluate_event(&mouse(DOWN, 1, 2)).unwrap();
+    assert_eq!(terminal.cursor(), Position { x: 4, y: 2 });
+    editor.evaluate_event(&mouse(DOWN, 15, 0)).unwrap();
+    assert_eq!(terminal.cursor(), Position { x: 10, y: 0 });
+    editor.evaluate_event(&mouse(DOWN, 3, 4)).unwrap();
+    assert_eq!(terminal.cursor(), Position { x: 10, y: 0 });
+}
+
+#[test]
+fn dragging_selects_like_visual_mode() {
+    let (mut editor, terminal, path) = editor_with("mouse-drag", "hello world\nagain", SIZE);
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    editor.evaluate_event(&mouse(DOWN, 2, 0)).unwrap();
+    editor.evaluate_event(&mouse(DRAG, 5, 0)).unwrap();
+    editor.evaluate_event(&mouse(DRAG, 8, 0)).unwrap();
+    editor.evaluate_event(&mouse(MouseEventKind::Up(MouseButton::Left), 8, 0)).unwrap();
+    assert!(terminal.row(4).starts_with(" VISUAL"));
+    type_text(&mut editor, "d");
+    editor.evaluate_event(&ctrl('s')).unwrap();
+    assert_eq!(fs::read_to_string(path).unwrap(), "held\nagain");
+}
+
+#[test]
+fn double_and_triple_clicks_select_a_word_and_a_line() {
+    let (mut editor, terminal, path) = editor_with("mouse-multi", "one two three\nfour", SIZE);
+    editor.evaluate_event(&mouse(DOWN, 5, 0)).unwrap();
+    editor.evaluate_event(&mouse(DOWN, 5, 0)).unwrap();
+    // From insert mode the word is taken in select mode, typing replaces it
+    assert!(terminal.row(4).starts_with(" SELECT"));
+    type_text(&mut editor, "2");
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    editor.evaluate_event(&mouse(DOWN, 1, 1)).unwrap();
+    editor.evaluate_event(&mouse(DOWN, 1, 1)).unwrap();
+    editor.evaluate_event(&mouse(DOWN, 1, 1)).unwrap();
+    assert!(terminal.row(4).starts_with(" V-LINE"));
+    type_text(&mut editor, "d");
+    editor.evaluate_event(&ctrl('s')).unwrap();
+    assert_eq!(fs::read_to_string(path).unwrap(), "one 2 three");
+}
+
+#[test]
+fn the_wheel_scrolls_without_moving_the_cursor() {
+    let contents: Vec<String> = (0..10).map(|n| format!("line {n}")).collect();
+    let (mut editor, terminal, _) = editor_with("mouse-wheel", &contents.join("\n"), SIZE);
+    editor.evaluate_event(&mouse(MouseEventKind::ScrollDown, 0, 0)).unwrap();
+    assert_eq!(text_rows(&terminal), ["line 3", "line 4", "line 5", "line 6"]);
+    assert!(!terminal.cursor_visible());
+    editor.evaluate_event(&mouse(MouseEventKind::ScrollDown, 0, 0)).unwrap();
+    editor.evaluate_event(&mouse(MouseEventKind::ScrollDown, 0, 0)).unwrap();
+    assert_eq!(text_rows(&terminal)[0], "line 6");
+    editor.evaluate_event(&mouse(MouseEventKind::ScrollUp, 0, 0)).unwrap();
+    assert_eq!(text_rows(&terminal)[0], "line 3");
+    // Typing brings the cursor back into view
+    type_text(&mut editor, "x");
+    assert_eq!(text_rows(&terminal)[0], "xline 0");
+    assert!(terminal.cursor_visible());
+    assert_eq!(terminal.cursor(), Position { x: 1, y: 0 });
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use common::{ctrl, editor_with, key, text_rows, type_text};
+use crab::Size;
+use crossterm::event::KeyCode;
+
+const SIZE: Size = Size { height: 10, width: 60 };
+
+#[test]
+fn palette_finds_commands_and_shows_their_keys() {
+    let (mut editor, terminal, _) = editor_with("palette-find", "one two", SIZE);
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    editor.evaluate_event(&ctrl('p')).unwrap();
+    type_text(&mut editor, "delchar");
+    assert_eq!(terminal.row(0), "> delchar");
+    assert!(terminal.row(1).starts_with(" delete-char  Delete the character under the cursor"));
+    assert!(terminal.row(1).ends_with(" x"));
+    editor.evaluate_event(&key(KeyCode::Enter)).unwrap();
+    assert_eq!(terminal.row(0), "ne two");
+}
+
+#[test]
+fn palette_ranks_recent_commands_and_cancels() {
+    let (mut editor, terminal, _) = editor_with("palette-recent", "one\ntwo", SIZE);
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    editor.evaluate_event(&ctrl('p')).unwrap();
+    type_text(&mut editor, "lastline");
+    editor.evaluate_event(&key(KeyCode::Enter)).unwrap();
+    assert_eq!(terminal.cursor().y, 1);
+
+    editor.evaluate_event(&ctrl('p')).unwrap();
+    assert!(terminal.row(1).starts_with(" last-line"));
+    editor.evaluate_event(&key(KeyCode::Down)).unwrap();
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    assert_eq!(text_rows(&terminal)[..3], ["one", "two", "~"]);
+    type_text(&mut editor, "x");
+    assert_eq!(terminal.row(1), "wo");
+}
+
+#[test]
+fn palette_leaves_out_its_own_commands() {
+    let (mut editor, terminal, _) = editor_with("palette-internal", "one two", SIZE);
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    editor.evaluate_event(&ctrl('p')).unwrap();
+    type_text(&mut editor, "palette-execute");
+    assert!(!terminal.rows().iter().any(|row| row.starts_with(" palette-")));
+    // Whatever comes up first runs once instead of running the palette again and again
+    editor.evaluate_event(&key(KeyCode::Enter)).unwrap();
+    assert!(terminal.row(8).starts_with(" NORMAL"));
+    editor.evaluate_event(&ctrl('p')).unwrap();
+    type_text(&mut editor, "lx");
+    assert!(!terminal.rows().iter().any(|row| row.starts_with(" palette-") || row.starts_with(" confirm-")));
+    editor.evaluate_event(&key(KeyCode::Enter)).unwrap();
+}
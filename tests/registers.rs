@@ -0,0 +1,118 @@
+mod common;
+
+use common::{alt, ctrl, editor_with, key, text_rows, type_text};
+use crab::{Position, Size};
+use crossterm::event::{Event, KeyCode};
+use std::fs;
+
+const SIZE: Size = Size { height: 8, width: 20 };
+
+#[test]
+fn multi_line_paste_splices_into_the_line() {
+    let (mut editor, terminal, _) = editor_with("registers-splice", "one two\nthree\nfour\nXY", SIZE);
+    editor.evaluate_event(&key(KeyCode::Right)).unwrap();
+    editor.evaluate_event(&key(KeyCode::Right)).unwrap();
+    editor.evaluate_event(&key(KeyCode::Right)).unwrap();
+    editor.evaluate_event(&alt('v')).unwrap();
+    editor.evaluate_event(&key(KeyCode::Down)).unwrap();
+    editor.evaluate_event(&key(KeyCode::Down)).unwrap();
+    editor.evaluate_event(&ctrl('c')).unwrap();
+    editor.evaluate_event(&key(KeyCode::Enter)).unwrap();
+
+    // The copy survives leaving visual mode
+    editor.evaluate_event(&key(KeyCode::Down)).unwrap();
+    editor.evaluate_event(&key(KeyCode::Left)).unwrap();
+    editor.evaluate_event(&ctrl('b')).unwrap();
+    assert_eq!(text_rows(&terminal)[..6], ["one two", "three", "four", "X two", "three", "fouY"]);
+    assert_eq!(terminal.cursor().y, 5);
+    assert_eq!(terminal.cursor().x, 3);
+
+    // The whole paste is undone in one step
+    editor.evaluate_event(&ctrl('z')).unwrap();
+    assert_eq!(text_rows(&terminal)[..4], ["one two", "three", "four", "XY"]);
+}
+
+#[test]
+fn named_registers_are_kept_apart() {
+    let (mut editor, terminal, _) = editor_with("registers-named", "ab", SIZE);
+    editor.evaluate_event(&alt('v')).unwrap();
+    editor.evaluate_event(&key(KeyCode::Right)).unwrap();
+    editor.evaluate_event(&key(KeyCode::Char('"'))).unwrap();
+    editor.evaluate_event(&key(KeyCode::Char('q'))).unwrap();
+    editor.evaluate_event(&ctrl('c')).unwrap();
+    editor.evaluate_event(&key(KeyCode::Right)).unwrap();
+    editor.evaluate_event(&ctrl('c')).unwrap();
+
+    // "q still holds "a" although the unnamed register now has "ab"
+    editor.evaluate_event(&key(KeyCode::Char('"'))).unwrap();
+    editor.evaluate_event(&key(KeyCode::Char('q'))).unwrap();
+    editor.evaluate_event(&ctrl('b')).unwrap();
+    editor.evaluate_event(&ctrl('b')).unwrap();
+    assert_eq!(terminal.row(0), "abaab");
+}
+
+#[test]
+fn yanks_reach_the_system_clipboard() {
+    let (mut editor, terminal, _) = editor_with("registers-clipboard", "copy me", SIZE);
+    editor.evaluate_event(&alt('v')).unwrap();
+    for _ in 0..4 {
+        editor.evaluate_event(&key(KeyCode::Right)).unwrap();
+    }
+    editor.evaluate_event(&ctrl('c')).unwrap();
+    assert_eq!(terminal.clipboard().as_deref(), Some("copy"));
+
+    // Named registers stay private to the editor
+    editor.evaluate_event(&key(KeyCode::Char('"'))).unwrap();
+    editor.evaluate_event(&key(KeyCode::Char('a'))).unwrap();
+    editor.evaluate_event(&key(KeyCode::Right)).unwrap();
+    editor.evaluate_event(&ctrl('c')).unwrap();
+    assert_eq!(terminal.clipboard().as_deref(), Some("copy"));
+}
+
+#[test]
+fn bracketed_paste_inserts_the_text_as_it_is() {
+    let (mut editor, terminal, path) = editor_with("registers-bracketed", "ab\ncd", SIZE);
+    editor.evaluate_event(&key(KeyCode::Right)).unwrap();
+    type_text(&mut editor, "x");
+    let frames = terminal.frames();
+    editor.evaluate_event(&Event::Paste("1\t2\r\n:q\r3".to_string())).unwrap();
+    // One redraw, with tabs and line breaks taken literally instead of as keys
+    assert_eq!(terminal.frames(), frames + 1);
+    assert_eq!(text_rows(&terminal)[1..4], [":q", "3b", "cd"]);
+    assert_eq!(terminal.cursor(), Position { x: 1, y: 2 });
+    editor.evaluate_event(&ctrl('s')).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "ax1\t2\n:q\n3b\ncd");
+    // Undone in one step, apart from the typing before it
+    editor.evaluate_event(&ctrl('z')).unwrap();
+    assert_eq!(text_rows(&terminal)[..2], ["axb", "cd"]);
+}
+
+#[test]
+fn bracketed_paste_replaces_the_selection_and_fills_prompts() {
+    let (mut editor, terminal, _) = editor_with("registers-bracketed-select", "one two", SIZE);
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    type_text(&mut editor, "vee");
+    editor.evaluate_event(&Event::Paste("three\nfour".to_string())).unwrap();
+    assert_eq!(text_rows(&terminal)[..2], ["three", "four"]);
+    assert!(terminal.row(6).starts_with(" NORMAL"));
+    type_text(&mut editor, "/");
+    editor.evaluate_event(&Event::Paste("our\nignored".to_string())).unwrap();
+    assert_eq!(terminal.row(7), "/our");
+}
+
+#[test]
+fn visual_paste_replaces_the_selection() {
+    let (mut editor, terminal, _) = editor_with("registers-visual-paste", "one two\nthree\nfour", SIZE);
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    type_text(&mut editor, "yewvep");
+    assert!(terminal.row(6).starts_with(" NORMAL"));
+    assert_eq!(text_rows(&terminal)[..3], ["one one", "three", "four"]);
+    // The replaced text is in the register now, undone in one step
+    type_text(&mut editor, "jVp");
+    assert_eq!(text_rows(&terminal)[..3], ["one one", "two", "four"]);
+    type_text(&mut editor, "u");
+    assert_eq!(text_rows(&terminal)[..3], ["one one", "three", "four"]);
+    // Lines over the last line
+    type_text(&mut editor, "yyGVp");
+    assert_eq!(text_rows(&terminal)[..4], ["one one", "three", "three", "~"]);
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use common::{ctrl, editor_with, key, text_rows, type_text};
+use crab::{Position, Size};
+use crossterm::event::KeyCode;
+use crossterm::style::Color;
+use std::fs;
+
+const SIZE: Size = Size { height: 6, width: 10 };
+
+#[test]
+fn typing_renders_text_and_moves_cursor() {
+    let (mut editor, terminal, _) = editor_with("typing", "", SIZE);
+    type_text(&mut editor, "hi\nyo");
+    assert_eq!(text_rows(&terminal), ["hi", "yo", "~", "~"]);
+    assert_eq!(terminal.cursor(), Position { x: 2, y: 1 });
+}
+
+#[test]
+fn backspace_joins_lines_and_clears_stale_rows() {
+    let (mut editor, terminal, _) = editor_with("backspace", "ab\ncd", SIZE);
+    editor.evaluate_event(&key(KeyCode::Down)).unwrap();
+    editor.evaluate_event(&key(KeyCode::Backspace)).unwrap();
+    assert_eq!(text_rows(&terminal), ["abcd", "~", "~", "~"]);
+    assert_eq!(terminal.cursor(), Position { x: 2, y: 0 });
+}
+
+#[test]
+fn long_lines_are_clipped_to_the_screen() {
+    let (_, terminal, _) = editor_with("clipped", "0123456789abcdef", SIZE);
+    assert_eq!(terminal.row(0), "012345678>");
+}
+
+#[test]
+fn viewport_scrolls_with_the_cursor() {
+    let contents: Vec<String> = (0..10).map(|n| format!("line {n}")).collect();
+    let (mut editor, terminal, _) = editor_with("scroll", &contents.join("\n"), SIZE);
+    for _ in 0..5 {
+        editor.evaluate_event(&key(KeyCode::Down)).unwrap();
+    }
+    assert_eq!(text_rows(&terminal), ["line 2", "line 3", "line 4", "line 5"]);
+    assert_eq!(terminal.cursor(), Position { x: 0, y: 3 });
+}
+
+#[test]
+fn ctrl_s_writes_the_buffer_back() {
+    let (mut editor, _, path) = editor_with("save", "world\n", SIZE);
+    type_text(&mut editor, "hello ");
+    editor.evaluate_event(&ctrl('s')).unwrap();
+    assert_eq!(fs::read_to_string(path).unwrap(), "hello world\n");
+}
+
+#[test]
+fn wide_graphemes_take_two_cells() {
+    let (mut editor, terminal, _) = editor_with("wide", "a日本語です\n", SIZE);
+    editor.evaluate_event(&key(KeyCode::Right)).unwrap();
+    editor.evaluate_event(&key(KeyCode::Right)).unwrap();
+    assert_eq!(terminal.cursor(), Position { x: 3, y: 0 });
+    // The last glyph only has half of its cells left on screen, the clip indicator takes it
+    assert_eq!(terminal.row(0), "a日本語で>");
+    assert_eq!(terminal.cell(Position { x: 2, y: 0 }).symbol, "");
+}
+
+#[test]
+fn cursor_moves_over_combining_sequences() {
+    let (mut editor, terminal, path) = editor_with("combining", "e\u{301}👍🏽x", SIZE);
+    editor.evaluate_event(&key(KeyCode::Right)).unwrap();
+    assert_eq!(terminal.cursor(), Position { x: 1, y: 0 });
+    editor.evaluate_event(&key(KeyCode::Right)).unwrap();
+    assert_eq!(terminal.cursor(), Position { x: 3, y: 0 });
+    editor.evaluate_event(&key(KeyCode::Backspace)).unwrap();
+    type_text(&mut editor, "é");
+    editor.evaluate_event(&ctrl('s')).unwrap();
+    assert_eq!(fs::read_to_string(path).unwrap(), "e\u{301}éx");
+    assert_eq!(terminal.cursor(), Position { x: 2, y: 0 });
+}
+
+#[test]
+fn status_bar_shows_the_file_mode_and_cursor() {
+    let (mut editor, terminal, path) = editor_with("status", "one\ntwo", Size { height: 5, width: 40 });
+    let name = path.file_name().unwrap().to_string_lossy().into_owned();
+    assert_eq!(terminal.row(3), format!(" INSERT  {name}{}1:1", " ".repeat(40 - 13 - name.len())));
+    type_text(&mut editor, "x");
+    assert!(terminal.row(3).starts_with(&format!(" INSERT  {name} [+]")));
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    editor.evaluate_event(&key(KeyCode::Down)).unwrap();
+    assert!(terminal.row(3).starts_with(" NORMAL"));
+    assert!(terminal.row(3).ends_with("2:2"));
+    editor.evaluate_event(&ctrl('s')).unwrap();
+    assert!(!terminal.row(3).contains("[+]"));
+    assert_eq!(terminal.row(4), format!("\"{}\" written", path.display()).chars().take(40).collect::<String>());
+    // The text area stops above the status bar
+    assert_eq!(terminal.rows()[..3], ["xone", "two", "~"]);
+}
+
+#[test]
+fn errors_show_in_red_on_the_message_line() {
+    let (mut editor, terminal, _) = editor_with("status-error", "", Size { height: 5, width: 40 });
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    type_text(&mut editor, ":frobnicate\n");
+    assert_eq!(terminal.row(4), "Not an editor command: frobnicate");
+    assert_eq!(terminal.cell(Position { x: 0, y: 4 }).foreground, Color::Red);
+    // Messages stay up while typing, until they expire or another one replaces them
+    type_text(&mut editor, "ix");
+    assert_eq!(terminal.row(4), "Not an editor command: frobnicate");
+}
+
+#[test]
+fn long_lines_scroll_sideways_with_the_cursor() {
+    let (mut editor, terminal, _) = editor_with("side-scroll", "0123456789abcdefghij\nxy", SIZE);
+    editor.set_side_scroll_margin(2);
+    for _ in 0..8 {
+        editor.evaluate_event(&key(KeyCode::Right)).unwrap();
+    }
+    // Scrolled as little as keeps two columns right of the cursor, with the clipped text marked
+    assert_eq!(text_rows(&terminal), ["<23456789>", "<", "~", "~"]);
+    assert_eq!(terminal.cursor(), Position { x: 7, y: 0 });
+    for _ in 0..9 {
+        editor.evaluate_event(&key(KeyCode::Right)).unwrap();
+    }
+    assert_eq!(text_rows(&terminal)[0], "<bcdefghij");
+    assert_eq!(terminal.cursor(), Position { x: 7, y: 0 });
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    editor.evaluate_event(&key(KeyCode::Home)).unwrap();
+    assert_eq!(text_rows(&terminal)[0], "012345678>");
+    assert_eq!(terminal.cursor(), Position { x: 0, y: 0 });
+}
+
+#[test]
+fn only_changed_cells_are_sent_to_the_terminal() {
+    let (mut editor, terminal, _) = editor_with("diff", "hello\nworld", SIZE);
+    let (printed, frames) = (terminal.printed(), terminal.frames());
+    type_text(&mut editor, "x");
+    // The line shifts right by one and the status bar gains [+], nothing else is redrawn
+    assert!(terminal.printed() - printed < 20, "printed {}", terminal.printed() - printed);
+    assert_eq!(terminal.frames(), frames + 1);
+    assert_eq!(text_rows(&terminal), ["xhello", "world", "~", "~"]);
+    assert!(terminal.cursor_visible());
+    assert_eq!(terminal.cursor(), Position { x: 1, y: 0 });
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::{editor_with, key, text_rows, type_text};
+use crab::Size;
+use crossterm::event::KeyCode;
+
+const SIZE: Size = Size { height: 7, width: 40 };
+
+#[test]
+fn replace_everywhere_with_capture_groups_in_one_undo_step() {
+    let (mut editor, terminal, _) = editor_with("replace-groups", "hello world\nfoo bar baz\nnothing", SIZE);
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    type_text(&mut editor, r":%s/(\w+) (\w+)/$2 \1/g");
+    editor.evaluate_event(&key(KeyCode::Enter)).unwrap();
+    assert_eq!(text_rows(&terminal)[..3], ["world hello", "bar foo baz", "nothing"]);
+    assert_eq!(terminal.row(6), "2 substitutions on 2 lines");
+    type_text(&mut editor, "u");
+    assert_eq!(text_rows(&terminal)[..3], ["hello world", "foo bar baz", "nothing"]);
+}
+
+#[test]
+fn multi_line_patterns_join_lines() {
+    let (mut editor, terminal, _) = editor_with("replace-multi-line", "a\nb\na\nc", SIZE);
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    type_text(&mut editor, r":%s/a\nb/[&]/");
+    editor.evaluate_event(&key(KeyCode::Enter)).unwrap();
+    assert_eq!(text_rows(&terminal)[..3], ["[a", "b]", "a"]);
+    assert_eq!(terminal.row(6), "1 substitution on 1 line");
+    type_text(&mut editor, r":%s/\n//g");
+    editor.evaluate_event(&key(KeyCode::Enter)).unwrap();
+    assert_eq!(terminal.row(0), "[ab]ac");
+    type_text(&mut editor, ":s/x/y/");
+    editor.evaluate_event(&key(KeyCode::Enter)).unwrap();
+    assert_eq!(terminal.row(6), "Pattern not found: x");
+}
+
+#[test]
+fn replace_in_the_selection_only() {
+    let (mut editor, terminal, _) = editor_with("replace-selection", "one\none\none", SIZE);
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    type_text(&mut editor, "jVj:");
+    assert_eq!(terminal.row(6), ":'<,'>");
+    type_text(&mut editor, "s/o/0/g\n");
+    assert_eq!(text_rows(&terminal)[..3], ["one", "0ne", "0ne"]);
+}
+
+#[test]
+fn confirm_asks_about_each_match() {
+    let (mut editor, terminal, _) = editor_with("replace-confirm", "x x\nx\nx", SIZE);
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    type_text(&mut editor, ":%s/x/y/gc\n");
+    assert_eq!(terminal.row(6), "replace with \"y\" (y/n/a/q)?");
+    type_text(&mut editor, "yn");
+    assert_eq!(text_rows(&terminal)[..3], ["y x", "x", "x"]);
+    assert_eq!((terminal.cursor().x, terminal.cursor().y), (0, 1));
+    type_text(&mut editor, "a");
+    assert_eq!(text_rows(&terminal)[..3], ["y x", "y", "y"]);
+    assert_eq!(terminal.row(6), "3 substitutions on 3 lines");
+    type_text(&mut editor, "u");
+    assert_eq!(text_rows(&terminal)[..3], ["x x", "x", "x"]);
+
+    // q stops where it is and keeps what was replaced
+    type_text(&mut editor, ":%s/x/z/gc\nyq");
+    assert_eq!(text_rows(&terminal)[..3], ["z x", "x", "x"]);
+    assert_eq!(terminal.row(6), "1 substitution on 1 line");
+}
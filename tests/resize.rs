@@ -0,0 +1,37 @@
+mod common;
+
+use common::{editor_with, key, text_rows};
+use crab::{Position, Size};
+use crossterm::event::{Event, KeyCode};
+
+const SIZE: Size = Size { height: 6, width: 10 };
+
+#[test]
+fn shrinking_keeps_the_cursor_in_view() {
+    let contents: Vec<String> = (0..10).map(|n| format!("line {n}")).collect();
+    let (mut editor, terminal, _) = editor_with("resize-shrink", &contents.join("\n"), SIZE);
+    for _ in 0..3 {
+        editor.evaluate_event(&key(KeyCode::Down)).unwrap();
+    }
+    terminal.set_size(Size { height: 4, width: 10 });
+    editor.evaluate_event(&Event::Resize(10, 4)).unwrap();
+    assert_eq!(text_rows(&terminal), ["line 2", "line 3"]);
+    assert_eq!(terminal.cursor(), Position { x: 0, y: 1 });
+    // The status bar moved up with the bottom of the screen
+    assert_eq!(terminal.row(2), " INSE 4:1");
+}
+
+#[test]
+fn growing_shows_more_and_rewraps() {
+    let (mut editor, terminal, _) = editor_with("resize-grow", "0123456789abcdef\nend", SIZE);
+    editor.set_soft_wrap(true);
+    for _ in 0..16 {
+        editor.evaluate_event(&key(KeyCode::Right)).unwrap();
+    }
+    assert_eq!(text_rows(&terminal), ["0123456789", "abcdef", "end", "~"]);
+    terminal.set_size(Size { height: 8, width: 20 });
+    editor.evaluate_event(&Event::Resize(20, 8)).unwrap();
+    assert_eq!(text_rows(&terminal), ["0123456789abcdef", "end", "~", "~", "~", "~"]);
+    assert_eq!(terminal.cursor(), Position { x: 16, y: 0 });
+    assert!(terminal.row(6).starts_with(" INSERT"));
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use common::{alt, ctrl, editor_with, key, text_rows, type_text};
+use crab::{Position, Size};
+use crossterm::event::KeyCode;
+use crossterm::style::Color;
+
+const SIZE: Size = Size { height: 7, width: 30 };
+
+fn numbered_lines(count: usize) -> String {
+    (0..count).map(|n| format!("line {n}")).collect::<Vec<_>>().join("\n")
+}
+
+#[test]
+fn search_moves_as_you_type_and_scrolls_to_the_match() {
+    let (mut editor, terminal, _) = editor_with("search-incremental", &numbered_lines(100), SIZE);
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    type_text(&mut editor, "/line 7");
+    assert_eq!(terminal.row(6), "/line 7");
+    assert_eq!(terminal.row(0), "line 3");
+    type_text(&mut editor, "5\n");
+    assert_eq!(text_rows(&terminal), ["line 71", "line 72", "line 73", "line 74", "line 75"]);
+    assert_eq!((terminal.cursor().x, terminal.cursor().y), (0, 4));
+    // The match is highlighted, the rest of the line isn't
+    let background = |x, y| terminal.cell(Position { x, y }).background;
+    assert_ne!(background(0, 4), Color::Reset);
+    assert_ne!(background(6, 4), Color::Reset);
+    assert_eq!(background(0, 3), Color::Reset);
+
+    // The only match, so both ways go round the end back to it
+    type_text(&mut editor, "N");
+    assert_eq!(terminal.row(6), "search hit TOP, continuing at");
+    type_text(&mut editor, "n");
+    assert_eq!(terminal.row(6), "search hit BOTTOM, continuing");
+    assert_eq!((terminal.cursor().x, terminal.cursor().y), (0, 4));
+
+    // Esc in normal mode clears the highlight
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    assert_eq!(background(0, terminal.cursor().y), Color::Reset);
+}
+
+#[test]
+fn escape_restores_the_cursor_and_scroll() {
+    let (mut editor, terminal, _) = editor_with("search-cancel", &numbered_lines(100), SIZE);
+    editor.evaluate_event(&key(KeyCode::Down)).unwrap();
+    editor.evaluate_event(&ctrl('f')).unwrap();
+    type_text(&mut editor, "line 50");
+    assert_eq!(terminal.row(0), "line 46");
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    assert_eq!(text_rows(&terminal)[..4], ["line 0", "line 1", "line 2", "line 3"]);
+    assert_eq!((terminal.cursor().x, terminal.cursor().y), (0, 1));
+    // Still in insert mode where the search started
+    type_text(&mut editor, "x");
+    assert_eq!(terminal.row(1), "xline 1");
+}
+
+#[test]
+fn case_sensitivity_toggles_in_the_prompt() {
+    let (mut editor, terminal, _) = editor_with("search-case", "Foo\nfoo", SIZE);
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    editor.evaluate_event(&key(KeyCode::Down)).unwrap();
+    type_text(&mut editor, "/foo");
+    assert_eq!(terminal.cursor(), Position { x: 4, y: 6 });
+    editor.evaluate_event(&alt('c')).unwrap();
+    assert_eq!(terminal.row(6), "/foo  [Aa]");
+    editor.evaluate_event(&key(KeyCode::Enter)).unwrap();
+    assert_eq!(terminal.cursor().y, 1);
+    // The setting sticks for the next search
+    editor.evaluate_event(&key(KeyCode::Up)).unwrap();
+    type_text(&mut editor, "/foo\n");
+    assert_eq!(terminal.cursor().y, 1);
+}
@@ -0,0 +1,144 @@
+mod common;
+
+use common::{alt, ctrl, editor_with, key, text_rows, type_text};
+use crab::{Editor, Position, Size};
+use crossterm::event::KeyCode;
+use crossterm::style::Color;
+
+const SIZE: Size = Size { height: 6, width: 10 };
+
+fn press(editor: &mut Editor, code: KeyCode, times: usize) {
+    for _ in 0..times {
+        editor.evaluate_event(&key(code)).unwrap();
+    }
+}
+
+#[test]
+fn selection_is_drawn_on_a_background() {
+    let (mut editor, terminal, _) = editor_with("selection-draw", "abcd\nef", SIZE);
+    press(&mut editor, KeyCode::Right, 1);
+    editor.evaluate_event(&alt('v')).unwrap();
+    press(&mut editor, KeyCode::Right, 2);
+    let background = |x, y| terminal.cell(Position { x, y }).background;
+    assert_eq!(background(0, 0), Color::Reset);
+    assert_ne!(background(1, 0), Color::Reset);
+    assert_ne!(background(2, 0), Color::Reset);
+    assert_eq!(background(3, 0), Color::Reset);
+
+    // Leaving visual mode clears the highlight
+    press(&mut editor, KeyCode::Esc, 1);
+    assert_eq!(background(1, 0), Color::Reset);
+    assert_eq!(text_rows(&terminal)[..2], ["abcd", "ef"]);
+}
+
+#[test]
+fn cut_removes_the_selection_into_the_clipboard() {
+    let (mut editor, terminal, _) = editor_with("selection-cut", "one\ntwo", SIZE);
+    press(&mut editor, KeyCode::Right, 2);
+    editor.evaluate_event(&alt('v')).unwrap();
+    press(&mut editor, KeyCode::Down, 1);
+    editor.evaluate_event(&ctrl('x')).unwrap();
+    assert_eq!(text_rows(&terminal)[..2], ["ono", "~"]);
+    assert_eq!(terminal.clipboard().as_deref(), Some("e\ntw"));
+
+    editor.evaluate_event(&ctrl('b')).unwrap();
+    assert_eq!(text_rows(&terminal)[..2], ["one", "two"]);
+}
+
+#[test]
+fn indent_dedent_and_case_change_lines() {
+    let (mut editor, terminal, _) = editor_with("selection-indent", "ab\ncd\nef", SIZE);
+    editor.evaluate_event(&alt('v')).unwrap();
+    press(&mut editor, KeyCode::Down, 1);
+    press(&mut editor, KeyCode::Right, 1);
+    press(&mut editor, KeyCode::Tab, 1);
+    assert_eq!(text_rows(&terminal)[..3], ["    ab", "    cd", "ef"]);
+
+    editor.evaluate_event(&alt('v')).unwrap();
+    press(&mut editor, KeyCode::BackTab, 1);
+    assert_eq!(text_rows(&terminal)[..3], ["ab", "    cd", "ef"]);
+
+    editor.evaluate_event(&alt('v')).unwrap();
+    press(&mut editor, KeyCode::Right, 1);
+    editor.evaluate_event(&alt('u')).unwrap();
+    assert_eq!(terminal.row(0), "Ab");
+
+    // Each operation is its own undo step
+    editor.evaluate_event(&ctrl('z')).unwrap();
+    editor.evaluate_event(&ctrl('z')).unwrap();
+    assert_eq!(text_rows(&terminal)[..3], ["    ab", "    cd", "ef"]);
+}
+
+#[test]
+fn typing_replaces_the_selection() {
+    let (mut editor, terminal, _) = editor_with("selection-replace", "hello world", SIZE);
+    editor.evaluate_event(&alt('v')).unwrap();
+    press(&mut editor, KeyCode::Right, 5);
+    type_text(&mut editor, "bye");
+    assert_eq!(terminal.row(0), "bye world");
+    assert_eq!(terminal.cursor(), Position { x: 3, y: 0 });
+}
+
+#[test]
+fn line_selection_takes_whole_lines() {
+    let (mut editor, terminal, _) = editor_with("selection-lines", "one\ntwo\nthree", SIZE);
+    press(&mut editor, KeyCode::Right, 1);
+    editor.evaluate_event(&alt('V')).unwrap();
+    press(&mut editor, KeyCode::Down, 1);
+    editor.evaluate_event(&ctrl('x')).unwrap();
+    assert_eq!(text_rows(&terminal)[..2], ["three", "~"]);
+    assert_eq!(terminal.clipboard().as_deref(), Some("one\ntwo\n"));
+}
+
+#[test]
+fn block_typing_inserts_on_every_line() {
+    let (mut editor, terminal, _) = editor_with("selection-block", "a|b\nc|d\ne|f", SIZE);
+    press(&mut editor, KeyCode::Right, 1);
+    editor.evaluate_event(&ctrl('v')).unwrap();
+    press(&mut editor, KeyCode::Down, 2);
+    type_text(&mut editor, "--");
+    assert_eq!(text_rows(&terminal)[..3], ["a--|b", "c--|d", "e--|f"]);
+
+    // Widening the block to the right replaces its columns
+    press(&mut editor, KeyCode::Right, 1);
+    editor.evaluate_event(&ctrl('c')).unwrap();
+    assert_eq!(terminal.clipboard().as_deref(), Some("|\n|\n|"));
+    type_text(&mut editor, "+");
+    assert_eq!(text_rows(&terminal)[..3], ["a--+b", "c--+d", "e--+f"]);
+    press(&mut editor, KeyCode::Esc, 1);
+
+    editor.evaluate_event(&ctrl('z')).unwrap();
+    assert_eq!(text_rows(&terminal)[..3], ["a--|b", "c--|d", "e--|f"]);
+}
+
+#[test]
+fn visual_mode_takes_the_grapheme_under_the_cursor() {
+    let (mut editor, terminal, path) = editor_with("selection-inclusive", "abc one\nab\ncd", SIZE);
+    press(&mut editor, KeyCode::Esc, 1);
+    type_text(&mut editor, "vd");
+    assert_eq!(terminal.row(0), "bc one");
+    type_text(&mut editor, "wved");
+    assert_eq!(terminal.row(0), "bc");
+    // Backwards the anchor's grapheme is taken too
+    type_text(&mut editor, "0lvhd");
+    assert_eq!(terminal.row(0), "");
+    // $ stops on the last grapheme in normal mode, so x deletes it
+    type_text(&mut editor, "j$x");
+    assert_eq!(terminal.cursor(), Position { x: 0, y: 1 });
+    editor.evaluate_event(&ctrl('s')).unwrap();
+    assert_eq!(std::fs::read_to_string(path).unwrap(), " \na\ncd");
+}
+
+#[test]
+fn deleting_the_last_lines_takes_the_line_break_before_them() {
+    let (mut editor, terminal, path) = editor_with("selection-last-line", "a\nb\nc", SIZE);
+    press(&mut editor, KeyCode::Esc, 1);
+    type_text(&mut editor, "Gdd");
+    assert_eq!(text_rows(&terminal)[..3], ["a", "b", "~"]);
+    assert_eq!(terminal.cursor(), Position { x: 0, y: 1 });
+    type_text(&mut editor, "yyP");
+    assert_eq!(text_rows(&terminal)[..3], ["a", "b", "b"]);
+    type_text(&mut editor, "ggVGd");
+    editor.evaluate_event(&ctrl('s')).unwrap();
+    assert_eq!(std::fs::read_to_string(path).unwrap(), "");
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::{editor_with, key, mouse, temp_path, text_rows, type_text};
+use crab::{LineNumbers, Position, Size};
+use crossterm::event::{KeyCode, MouseButton, MouseEventKind};
+use std::fs;
+
+const SIZE: Size = Size { height: 6, width: 10 };
+
+#[test]
+fn long_lines_wrap_at_words_after_the_indicator() {
+    let (mut editor, terminal, _) = editor_with("wrap-words", "one two three fourteen\nend", SIZE);
+    editor.set_soft_wrap(true);
+    editor.set_wrap_indicator("> ");
+    type_text(&mut editor, "x");
+    assert_eq!(text_rows(&terminal), ["xone two", "> three", "> fourteen", "end"]);
+    editor.set_line_numbers(LineNumbers::Absolute);
+    editor.evaluate_event(&key(KeyCode::Up)).unwrap();
+    assert_eq!(text_rows(&terminal), ["  1 xone", "    > two", "    > thre", "    > e"]);
+}
+
+#[test]
+fn up_and_down_move_by_screen_rows() {
+    let (mut editor, terminal, _) = editor_with("wrap-rows", "abcdefghijklmnopqrstuvwxy\nshort", SIZE);
+    let path = temp_path("wrap-rows.toml");
+    fs::write(&path, "[editor]\nsoft-wrap = true\nwrap-at-words = false\n").unwrap();
+    editor.load_config(&path).unwrap();
+    editor.evaluate_event(&key(KeyCode::Right)).unwrap();
+    editor.evaluate_event(&key(KeyCode::Down)).unwrap();
+    assert_eq!(text_rows(&terminal), ["abcdefghij", "klmnopqrst", "uvwxy", "short"]);
+    assert_eq!(terminal.cursor(), Position { x: 1, y: 1 });
+    editor.evaluate_event(&key(KeyCode::Down)).unwrap();
+    editor.evaluate_event(&key(KeyCode::Down)).unwrap();
+    assert_eq!(terminal.cursor(), Position { x: 1, y: 3 });
+    // j and k in normal mode too, with a count
+    editor.evaluate_event(&key(KeyCode::Esc)).unwrap();
+    type_text(&mut editor, "2k");
+    assert_eq!(terminal.cursor(), Position { x: 1, y: 1 });
+    type_text(&mut editor, "x");
+    assert_eq!(text_rows(&terminal)[..2], ["abcdefghij", "kmnopqrstu"]);
+}
+
+#[test]
+fn the_viewport_scrolls_by_rows_through_a_wrapped_line() {
+    let long = "0123456789".repeat(6);
+    let (mut editor, terminal, _) = editor_with("wrap-scroll", &format!("{long}\nlast"), SIZE);
+    editor.set_soft_wrap(true);
+    for _ in 0..5 {
+        editor.evaluate_event(&key(KeyCode::Down)).unwrap();
+    }
+    assert_eq!(text_rows(&terminal), ["0123456789", "0123456789", "0123456789", "0123456789"]);
+    assert_eq!(terminal.cursor(), Position { x: 0, y: 3 });
+    editor.evaluate_event(&key(KeyCode::Down)).unwrap();
+    assert_eq!(text_rows(&terminal)[3], "last");
+    assert_eq!(terminal.cursor(), Position { x: 0, y: 3 });
+    editor.set_soft_wrap(false);
+    editor.evaluate_event(&key(KeyCode::Up)).unwrap();
+    assert_eq!(text_rows(&terminal), ["012345678>", "last", "~", "~"]);
+}
+
+#[test]
+fn clicks_land_on_the_wrapped_row_under_them() {
+    let (mut editor, terminal, _) = editor_with("wrap-click", "abcdefghijklmnopqrstuvwxy\nshort", SIZE);
+    editor.set_soft_wrap(true);
+    editor.set_wrap_indicator("> ");
+    let click = |column, row| mouse(MouseEventKind::Down(MouseButton::Left), column, row);
+    editor.evaluate_event(&click(4, 1)).unwrap();
+    type_text(&mut editor, "X");
+    assert_eq!(text_rows(&terminal)[..2], ["abcdefghij", "> klXmnopq"]);
+    // Left of the indicator is the start of the row
+    editor.evaluate_event(&click(0, 2)).unwrap();
+    assert_eq!(terminal.cursor(), Position { x: 2, y: 2 });
+}